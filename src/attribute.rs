@@ -0,0 +1,81 @@
+// Documentation doesn't have to come as comments: `///` and `//!` are just
+// sugar for `#[doc = "..."]` and `#![doc = "..."]`, which macros generate and
+// crates use to pull in their README via `include_str!`. We recognise these
+// attributes when they are written on a single line.
+
+pub(crate) struct DocAttribute {
+    // `#![doc]` documenting the enclosing item, rather than `#[doc]`
+    pub(crate) inner: bool,
+    pub(crate) value: DocValue,
+}
+
+pub(crate) enum DocValue {
+    // the documentation itself
+    Text(String),
+    // `include_str!` of the given path, relative to the source file
+    Include(String),
+}
+
+// Parse the `line` as a doc attribute, `None` if it is anything else.
+pub(crate) fn parse_doc(line: &str) -> Option<DocAttribute> {
+    let line = line.trim();
+    let (inner, rest) = match line.strip_prefix("#![") {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix("#[")?),
+    };
+    let rest = rest.strip_suffix(']')?.trim()
+        .strip_prefix("doc")?.trim_start()
+        .strip_prefix('=')?.trim();
+
+    let value = match rest.strip_prefix("include_str!") {
+        Some(args) => {
+            let args = args.trim().strip_prefix('(')?.strip_suffix(')')?.trim();
+            DocValue::Include(parse_str_literal(args)?)
+        }
+        None => DocValue::Text(parse_str_literal(rest)?),
+    };
+    Some(DocAttribute { inner, value })
+}
+
+// The value of a (raw) string literal, which has to make up all of `s`.
+fn parse_str_literal(s: &str) -> Option<String> {
+    if let Some(raw) = s.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let closing = format!("\"{}", "#".repeat(hashes));
+        return raw[hashes..].strip_prefix('"')?
+            .strip_suffix(closing.as_str())
+            .map(|v| v.to_string());
+    }
+
+    let mut chars = s.strip_prefix('"')?.chars();
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => value.push(match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                'x' => {
+                    let code: String = chars.by_ref().take(2).collect();
+                    char::from(u8::from_str_radix(&code, 16).ok()?)
+                }
+                'u' => {
+                    let code: String = chars.by_ref()
+                        .skip_while(|c| *c == '{')
+                        .take_while(|c| *c != '}')
+                        .collect();
+                    std::char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                c => c,
+            }),
+            c => value.push(c),
+        }
+    }
+    // nothing may follow the closing quote
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(value)
+}
@@ -0,0 +1,140 @@
+// `cargo dadada` renders the packages of the workspace we are run in, the
+// same way `dadada --manifest-path` does, into one page per package under
+// `dadada/` of cargo's target directory. Cargo runs us as `cargo-dadada dadada <args>`.
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use dadada::{Error, ExtractOptions, Package, TargetKind,
+    build_html, default_packages, extract_package, find_manifest, read_manifest, workspace_root};
+
+fn main() {
+    let matches = App::new("cargo")
+        .bin_name("cargo")
+        .setting(AppSettings::SubcommandRequired)
+        .subcommand(SubCommand::with_name("dadada")
+            .version("0.9.5-dev")
+            .about("Render the packages of a workspace with dadada")
+            .arg(Arg::with_name("manifest_path")
+                .long("manifest-path")
+                .value_name("PATH")
+                .help("Path to Cargo.toml, looked for in the current directory and its parents if not given")
+                .takes_value(true))
+            .arg(Arg::with_name("package")
+                .short("p")
+                .long("package")
+                .value_name("SPEC")
+                .help("Package to render")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("workspace")
+                .long("workspace")
+                .alias("all")
+                .help("Render all packages in the workspace"))
+            .arg(Arg::with_name("example")
+                .long("example")
+                .value_name("NAME")
+                .help("Render only the given example")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("lossy")
+                .long("lossy")
+                .help("Decode files that aren't valid UTF-8 anyway, with a warning, instead of failing"))
+            .arg(Arg::with_name("template_dir")
+                .long("template-dir")
+                .value_name("DIR")
+                .help("Directory with templates to use instead of the built-in ones")
+                .takes_value(true))
+            .arg(Arg::with_name("out_dir")
+                .long("out-dir")
+                .value_name("DIR")
+                .help("Directory to write to, `dadada` in cargo's target directory if not given")
+                .takes_value(true)))
+        .get_matches();
+
+    let matches = matches.subcommand_matches("dadada").expect("Subcommand is required");
+    if let Err(err) = run(matches) {
+        eprintln!("error: {}", err);
+        if let Error::Encoding { .. } = err {
+            eprintln!("note: pass `--lossy` to decode it anyway");
+        }
+        process::exit(err.exit_code());
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let manifest = match matches.value_of("manifest_path") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let cwd = env::current_dir().map_err(|source| Error::Io { path: ".".into(), source })?;
+            find_manifest(&cwd).ok_or_else(|| Error::Manifest {
+                path: cwd.join("Cargo.toml"),
+                message: "could not find `Cargo.toml` in the current directory or any parent".to_string(),
+            })?
+        }
+    };
+    let root = workspace_root(&manifest)?;
+    let workspace = read_manifest(&root)?;
+
+    // like cargo: the given packages, all of them, or the default ones of the
+    // manifest we are at
+    let mut packages: Vec<Package> = if let Some(names) = matches.values_of("package") {
+        names.map(|name| workspace.iter().find(|p| p.name == name).cloned()
+            .ok_or_else(|| Error::Manifest {
+                path: root.clone(),
+                message: format!("package `{}` is not a member of the workspace", name),
+            }))
+            .collect::<Result<_, _>>()?
+    } else if matches.is_present("workspace") {
+        workspace
+    } else {
+        default_packages(&manifest)?
+    };
+
+    if let Some(examples) = matches.values_of("example") {
+        let examples: Vec<&str> = examples.collect();
+        for package in packages.iter_mut() {
+            package.targets.retain(|t| t.kind == TargetKind::Example && examples.contains(&t.name.as_str()));
+            package.config.targets = None;
+        }
+        packages.retain(|p| !p.targets.is_empty());
+        for name in examples {
+            if !packages.iter().any(|p| p.targets.iter().any(|t| t.name == name)) {
+                return Err(Error::Manifest {
+                    path: manifest,
+                    message: format!("no example target named `{}`", name),
+                });
+            }
+        }
+    }
+
+    // cargo's target directory is `$CARGO_TARGET_DIR`, or `target` of the workspace
+    let out_dir = match (matches.value_of("out_dir"), env::var_os("CARGO_TARGET_DIR")) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(target_dir)) => PathBuf::from(target_dir).join("dadada"),
+        (None, None) => root.parent().unwrap_or_else(|| Path::new("")).join("target").join("dadada"),
+    };
+    fs::create_dir_all(&out_dir).map_err(|source| Error::Io { path: out_dir.clone(), source })?;
+
+    for package in packages.iter() {
+        let extract_options = ExtractOptions {
+            prose: package.config.prose.unwrap_or_default(),
+            lossy: matches.is_present("lossy"),
+            ..ExtractOptions::default()
+        };
+        let blocks = extract_package(package, &extract_options)?;
+        let mut options = package.options();
+        if let Some(dir) = matches.value_of("template_dir") {
+            options.template_dir = Some(dir.to_string());
+        }
+        let output = build_html(blocks, options)?;
+        let target = out_dir.join(format!("{}.html", package.name));
+        fs::write(&target, output).map_err(|source| Error::Io { path: target.clone(), source })?;
+        eprintln!("    Rendered {} to {}", package.name, target.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Helpers for `build.rs` scripts, rendering a crate's examples as part of
+//! its build. With `dadada` in `[build-dependencies]`, the `main` of the
+//! build script only needs:
+//!
+//! ```no_run
+//! dadada::build::render_examples().expect("examples render");
+//! ```
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::{ExtractOptions, Options, SourceFile, TargetKind, build_html, extract_crate, read_manifest};
+
+fn env_dir(name: &str) -> Result<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::Environment { name: name.to_string() })
+}
+
+// Make the path of `file`, and of the modules it links to, relative to `dir`
+// if they are within it
+fn relative_to(file: &mut SourceFile, dir: &Path) {
+    let strip = |path: &mut PathBuf| if let Ok(relative) = path.strip_prefix(dir) {
+        *path = relative.to_path_buf();
+    };
+    strip(&mut file.path);
+    for block in file.blocks.iter_mut() {
+        for (_, declared) in block.module_links.iter_mut() {
+            strip(declared);
+        }
+    }
+}
+
+/// Render every example of the crate being built to
+/// `$OUT_DIR/dadada/<example>.html`, returning the files written. Cargo is
+/// told to only run the build script again when an example changes.
+pub fn render_examples() -> Result<Vec<PathBuf>> {
+    let manifest_dir = env_dir("CARGO_MANIFEST_DIR")?;
+    let out_dir = env_dir("OUT_DIR")?;
+    render_examples_in(manifest_dir, out_dir.join("dadada"), &Options::default())
+}
+
+/// Render every example of the package at `manifest_dir` to
+/// `<out_dir>/<example>.html` with the given `options`, each titled after
+/// its example. The examples are those cargo knows of: `[[example]]`s of
+/// `Cargo.toml`, and unless `autoexamples` is off, `examples/<example>.rs`
+/// and `examples/<example>/main.rs` with the modules they declare. Prints
+/// the `cargo:rerun-if-changed` lines for them.
+pub fn render_examples_in<P, Q>(manifest_dir: P, out_dir: Q, options: &Options) -> Result<Vec<PathBuf>>
+    where P: AsRef<Path>, Q: AsRef<Path>
+{
+    let manifest_dir = manifest_dir.as_ref();
+    let manifest = manifest_dir.join("Cargo.toml");
+    let examples_dir = manifest_dir.join("examples");
+    let out_dir = out_dir.as_ref();
+    // the manifest, for the examples listed there and to notice a new
+    // `examples/`, and the directory, for added and removed examples. A path
+    // that doesn't exist would have cargo run the script on every build.
+    println!("cargo:rerun-if-changed={}", manifest.display());
+    if examples_dir.is_dir() {
+        println!("cargo:rerun-if-changed={}", examples_dir.display());
+    }
+
+    let examples: Vec<_> = read_manifest(&manifest)?.into_iter()
+        .take(1)
+        .flat_map(|p| p.targets)
+        .filter(|t| t.kind == TargetKind::Example)
+        .collect();
+
+    fs::create_dir_all(out_dir).map_err(|source| Error::Io { path: out_dir.into(), source })?;
+    let mut rendered = Vec::new();
+    for example in examples {
+        let mut files = extract_crate(&example.path, &ExtractOptions::default())?;
+        for file in files.iter_mut() {
+            println!("cargo:rerun-if-changed={}", file.path.display());
+            // shown relative to `examples/`, or the package for those elsewhere
+            relative_to(file, &examples_dir);
+            relative_to(file, manifest_dir);
+        }
+        let output = build_html(files, Options { title: example.name.clone(), ..options.clone() })?;
+
+        let target = out_dir.join(format!("{}.html", example.name));
+        fs::write(&target, output).map_err(|source| Error::Io { path: target.clone(), source })?;
+        rendered.push(target);
+    }
+    Ok(rendered)
+}
@@ -0,0 +1,25 @@
+// Directives are comments of the form `// dadada: <name>`, allowing authors to
+// control what ends up in the rendered output right from the source.
+
+pub(crate) enum Directive {
+    // leave out everything up to the next `skip-end`
+    SkipStart,
+    SkipEnd,
+    // leave out the next item, along with its comments and attributes
+    HideNextItem,
+    // start a new block with the next line
+    Break,
+}
+
+// Parse the content of a comment. `None` if it isn't a directive at all,
+// `Some(Err(name))` if it is one we don't know.
+pub(crate) fn parse(comment: &str) -> Option<Result<Directive, &str>> {
+    let name = comment.trim().strip_prefix("dadada:")?.trim();
+    Some(match name {
+        "skip-start" => Ok(Directive::SkipStart),
+        "skip-end" => Ok(Directive::SkipEnd),
+        "hide-next-item" => Ok(Directive::HideNextItem),
+        "break" => Ok(Directive::Break),
+        _ => Err(name),
+    })
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that may go wrong while extracting and rendering
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing the file at `path` failed
+    Io { path: PathBuf, source: io::Error },
+    /// The file at `path` isn't valid UTF-8 in the given (1-based) line
+    Encoding { path: PathBuf, line: usize },
+    /// An extra file to include in the output could not be found
+    MissingInclude { path: PathBuf, source: io::Error },
+    /// A template could not be rendered
+    Template { name: String, message: String },
+    /// The `Cargo.toml` at `path` isn't one we understand
+    Manifest { path: PathBuf, message: String },
+    /// The environment variable `name`, usually set by cargo, is missing
+    Environment { name: String },
+}
+
+/// Something worth pointing out, which doesn't stop us from rendering
+#[derive(Debug)]
+pub struct Warning {
+    /// The file the warning is about
+    pub path: PathBuf,
+    /// The (1-based) line the warning is about
+    pub line: usize,
+    /// What we want to point out
+    pub message: String,
+}
+
+/// `Result` with our [`Error`](enum.Error.html)
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The exit code the command line tool reports this error with
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io { .. } => 2,
+            Error::Encoding { .. } => 3,
+            Error::MissingInclude { .. } => 4,
+            Error::Template { .. } => 5,
+            Error::Manifest { .. } => 6,
+            Error::Environment { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } =>
+                write!(f, "could not access `{}`: {}", path.display(), source),
+            Error::Encoding { path, line } =>
+                write!(f, "`{}` is not valid UTF-8 (line {})", path.display(), line),
+            Error::MissingInclude { path, source } =>
+                write!(f, "could not include `{}`: {}", path.display(), source),
+            Error::Template { name, message } =>
+                write!(f, "failed to render template `{}`: {}", name, message),
+            Error::Manifest { path, message } =>
+                write!(f, "{}: {}", path.display(), message),
+            Error::Environment { name } =>
+                write!(f, "`{}` is not set, are we run by cargo?", name),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::MissingInclude { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
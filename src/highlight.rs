@@ -0,0 +1,123 @@
+// Rust code is highlighted while rendering, so that it is colourful without
+// any scripts too. Tokens get the classes Prism's Rust grammar gives them,
+// which is what `style.css` colours. The lexer tells comments and literals
+// apart from code already, so all that's left to do here is to split the
+// code in between into keywords, numbers, punctuation and so on.
+use crate::html::escape;
+use crate::language::RUST;
+use crate::lexer::{Lexer, Token, is_ident};
+
+const KEYWORDS: &[&str] = &["abstract", "alignof", "as", "async", "await", "be", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "match", "mod", "move", "mut", "offsetof", "once", "override", "priv", "pub", "pure",
+    "ref", "return", "sizeof", "static", "self", "Self", "struct", "super", "true", "trait", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield"];
+
+// Longest first, so that `<<=` isn't taken for `<` and `<=`
+const OPERATORS: &[&str] = &["<<=", ">>=", "&&", "&=", "||", "|=", "<<", ">>", "<=", ">=", "==", "=>",
+    "-=", "+=", "*=", "/=", "%=", "!=", "^=", "-", "+", "*", "/", "%", "!", "^", "=", "@", "&", "|", "<", ">"];
+
+fn push_token(output: &mut String, class: Option<&str>, text: &str) {
+    match class {
+        Some(_) if text.is_empty() => {}
+        Some(class) => output.push_str(&format!("<span class=\"token {}\">{}</span>", class, escape(text))),
+        None => output.push_str(&escape(text)),
+    }
+}
+
+// The `code` between comments and literals, as HTML
+fn push_code(output: &mut String, code: &str) {
+    let chars: Vec<(usize, char)> = code.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|c| c.1);
+    let pos = |i: usize| chars.get(i).map(|c| c.0).unwrap_or(code.len());
+    // the first index from `i` on at which `f` doesn't hold
+    let skip = |i: usize, f: &dyn Fn(char) -> bool| (i..).find(|&j| !at(j).map(f).unwrap_or(false))
+        .expect("`at` ends eventually");
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        let rest = &code[pos(i)..];
+        // where the token starting at `i` ends, and its class
+        let (end, class) = if rest.starts_with("#[") || rest.starts_with("#![") {
+            // up to the matching bracket, or the end of the code if there's a
+            // literal in between
+            let mut depth = 0;
+            let end = (i..chars.len())
+                .find(|&j| {
+                    match chars[j].1 {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => return false,
+                    }
+                    depth == 0
+                })
+                .map_or(chars.len(), |j| j + 1);
+            (end, Some("attribute attr-name"))
+        } else if c == '\'' {
+            // char literals are taken by the lexer, so this is a lifetime or label
+            (skip(i + 1, &is_ident), Some("lifetime-annotation symbol"))
+        } else if c.is_ascii_digit() {
+            // digits, suffixes like `u8`, a fraction and an exponent
+            let mut end = i;
+            while let Some(d) = at(end) {
+                let exponent_sign = matches!(d, '+' | '-') && matches!(at(end - 1), Some('e') | Some('E'))
+                    && !rest.starts_with("0x");
+                let fraction = d == '.' && at(end + 1).map(|f| f.is_ascii_digit()).unwrap_or(false);
+                if !(is_ident(d) || exponent_sign || fraction) {
+                    break;
+                }
+                end += 1;
+            }
+            (end, Some("number"))
+        } else if is_ident(c) {
+            let end = skip(i, &is_ident);
+            let word = &code[pos(i)..pos(end)];
+            let called = at(skip(end, &char::is_whitespace)) == Some('(');
+            if KEYWORDS.contains(&word) {
+                (end, Some("keyword"))
+            } else if at(end) == Some('!') {
+                let next = at(skip(end + 1, &char::is_whitespace));
+                (end + 1, Some(if matches!(next, Some('(') | Some('[')) { "function" } else { "macro-rules function" }))
+            } else if called {
+                (end, Some("function"))
+            } else {
+                (end, None)
+            }
+        } else if "{}[];(),:".contains(c) {
+            (i + 1, Some("punctuation"))
+        } else if c == '.' {
+            (skip(i, &|d| d == '.'), Some("punctuation"))
+        } else if rest.starts_with("->") {
+            (i + 2, Some("punctuation"))
+        } else if let Some(operator) = OPERATORS.iter().find(|o| rest.starts_with(*o)) {
+            (i + operator.len(), Some("operator"))
+        } else {
+            (skip(i + 1, &|d| !(is_ident(d) || d.is_ascii_punctuation())), None)
+        };
+        push_token(output, class, &code[pos(i)..pos(end)]);
+        i = end;
+    }
+}
+
+// The Rust source `lines` as HTML
+pub(crate) fn highlight(lines: &[&str]) -> String {
+    let mut lexer = Lexer::new(&RUST);
+    let mut output = String::new();
+    for (n, line) in lines.iter().enumerate() {
+        if n > 0 {
+            output.push('\n');
+        }
+        for token in lexer.line(line) {
+            match token {
+                Token::Code(code) => push_code(&mut output, code),
+                Token::Literal(text) if text.starts_with('\'') || text.starts_with("b'") =>
+                    push_token(&mut output, Some("char string"), text),
+                Token::Literal(text) => push_token(&mut output, Some("string"), text),
+                Token::LineComment(_, text) | Token::BlockComment { text, .. } =>
+                    push_token(&mut output, Some("comment"), text),
+            }
+        }
+    }
+    output
+}
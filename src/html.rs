@@ -0,0 +1,344 @@
+// The HTML page: every file is introduced by a header, and a package or target
+// by an intro, before its blocks. Styles and scripts are inlined. Headings in
+// the prose get anchors, and with the files make up the table of contents.
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, Parser, Tag, html};
+
+use crate::error::{Error, Result};
+use crate::highlight::highlight;
+use crate::language::RUST;
+use crate::render::Renderer;
+use crate::template::{AnnotationContext, BlockContext, IndexEntry, ModuleLinkContext, Nav, Page, Templates,
+    TocEntry};
+use crate::{Block, Options, SourceFile};
+
+/// Renders files into a single HTML page, through the templates
+pub struct HtmlRenderer {
+    options: Options,
+    templates: Templates,
+    page: Option<Page>,
+    output: String,
+    anchors: HashSet<String>,
+    // sections are numbered across all files, counting the file headers
+    section: usize,
+    // path of the file we are in
+    file: String,
+    // the block we are in, for its code and end
+    block: Option<BlockContext>,
+    // the files and headings so far
+    toc: Vec<TocEntry>,
+    pub(crate) site: Option<SitePage>,
+}
+
+// Where the page is within a site of one page per file
+pub(crate) struct SitePage {
+    // the way up from the page to the root of the site, like `../`
+    pub(crate) root: String,
+    // the page of every file, relative to the root
+    pub(crate) pages: HashMap<PathBuf, String>,
+    pub(crate) nav: Option<Nav>,
+}
+
+// The styles and scripts, inlined or written next to the pages of a site
+pub(crate) const CSS: &str = include_str!("static/style.css");
+pub(crate) const CSS_FILE: &str = "dadada.css";
+pub(crate) const JS_FILE: &str = "dadada.js";
+
+pub(crate) fn js() -> String {
+    [include_str!("static/prism.min.js"), include_str!("static/prism-rust.min.js"),
+        include_str!("static/line-numbers.js"), include_str!("static/highlighted.js"), include_str!("static/toc.js")].concat()
+}
+
+// The anchor of the header of the file at `path`
+fn file_anchor(path: &Path) -> String {
+    let path: String = path.to_string_lossy().chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' || c == '.' { c } else { '-' })
+        .collect();
+    format!("file.{}", path)
+}
+
+// `anchor`, or if that is taken already, the first free one of `anchor-2`,
+// `anchor-3` and so on.
+fn unique_anchor(anchors: &mut HashSet<String>, anchor: String) -> String {
+    let anchor = if anchors.contains(&anchor) {
+        (2..).map(|n| format!("{}-{}", anchor, n))
+            .find(|a| !anchors.contains(a))
+            .expect("There is always a free one")
+    } else {
+        anchor
+    };
+    anchors.insert(anchor.clone());
+    anchor
+}
+
+// `text` as HTML
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+// The anchor of a heading reading `text`, like `a-heading`
+fn heading_anchor(text: &str) -> String {
+    let words: Vec<String> = text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() { "heading".to_string() } else { words.join("-") }
+}
+
+// The markdown `source` as HTML
+pub(crate) fn markdown(source: &str) -> String {
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, Parser::new(source));
+    html_output
+}
+
+// The extra content in `file`, rendered if it is markdown
+fn include_static(file: &Option<String>) -> Result<String> {
+    let file = match file {
+        Some(file) => file,
+        None => return Ok(String::new()),
+    };
+    let path = Path::new(file);
+    let is_md = if let Some(ext) = path.extension() {
+        matches!(ext.to_str(), Some("md") | Some("mdown") | Some("markdown"))
+    } else {
+        false
+    };
+
+    let mut f = File::open(path)
+        .map_err(|source| Error::MissingInclude { path: path.into(), source })?;
+    let mut source = String::new();
+    f.read_to_string(&mut source)
+        .map_err(|source| Error::Io { path: path.into(), source })?;
+    Ok(if is_md { markdown(&source) } else { source })
+}
+
+impl HtmlRenderer {
+    /// A renderer with the given `options`, failing if the template
+    /// directory doesn't exist
+    pub fn new(options: Options) -> Result<HtmlRenderer> {
+        let templates = Templates::new(options.template_dir.as_deref().map(Path::new))?;
+        Ok(HtmlRenderer {
+            options,
+            templates,
+            page: None,
+            output: String::new(),
+            anchors: HashSet::new(),
+            section: 0,
+            file: String::new(),
+            block: None,
+            toc: Vec::new(),
+            site: None,
+        })
+    }
+
+    // What the head and foot of the page are rendered with
+    fn page(&self) -> Result<Page> {
+        let options = &self.options;
+        // a site shares its styles and scripts between the pages
+        let root = self.site.as_ref().map(|site| site.root.as_str());
+        Ok(Page {
+            title: options.title.clone(),
+            css: if options.with_css && root.is_none() { Some(CSS) } else { None },
+            js: if options.with_js && root.is_none() { Some(js()) } else { None },
+            stylesheet: root.filter(|_| options.with_css).map(|root| format!("{}{}", root, CSS_FILE)),
+            script: root.filter(|_| options.with_js).map(|root| format!("{}{}", root, JS_FILE)),
+            meta: include_static(&options.extra_meta)?,
+            header: include_static(&options.extra_header)?,
+            footer: include_static(&options.extra_footer)?,
+            nav: self.site.as_ref().and_then(|site| site.nav.clone()),
+            toc: Vec::new(),
+        })
+    }
+
+    // The index of a site, listing its files
+    pub(crate) fn index(&self, files: Vec<IndexEntry>) -> Result<String> {
+        let page = self.page()?;
+        let mut output = self.templates.render("head.html", &page)?;
+        output.push_str(&self.templates.render("index.html",
+            minijinja::context!(title => page.title, files => files))?);
+        output.push_str(&self.templates.render("foot.html", &page)?);
+        Ok(output)
+    }
+
+    // The markdown `source` of a comment as HTML, with anchors on its headings.
+    // `#` and `##` ones go into the table of contents.
+    fn prose(&mut self, source: &str) -> String {
+        let mut events = Vec::new();
+        let mut parser = Parser::new(source);
+        while let Some(event) = parser.next() {
+            let level = match event {
+                Event::Start(Tag::Header(level)) => level,
+                event => {
+                    events.push(event);
+                    continue;
+                }
+            };
+            let mut inner = Vec::new();
+            let mut text = String::new();
+            for event in parser.by_ref() {
+                match &event {
+                    Event::End(Tag::Header(_)) => break,
+                    Event::Text(t) | Event::Code(t) => text.push_str(t),
+                    _ => {}
+                }
+                inner.push(event);
+            }
+
+            let anchor = unique_anchor(&mut self.anchors, heading_anchor(&text));
+            let mut content = String::new();
+            html::push_html(&mut content, inner.into_iter());
+            if self.options.with_toc && level <= 2 {
+                self.toc.push(TocEntry {
+                    href: format!("#{}", anchor),
+                    title: escape(&text),
+                    level,
+                });
+            }
+            events.push(Event::Html(format!("<h{0} id=\"{1}\">{2}</h{0}>\n", level, anchor, content).into()));
+        }
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, events.into_iter());
+        html_output
+    }
+
+    // A block without code, numbered as the next section
+    fn context(&self, kind: &'static str, anchor: String, comment: String) -> BlockContext {
+        BlockContext {
+            title: self.options.title.clone(),
+            kind,
+            file: self.file.clone(),
+            index: self.section,
+            anchor,
+            heading: String::new(),
+            comment,
+            start_line: None,
+            end_line: None,
+            code: String::new(),
+            lines: 0,
+            line_numbers: Vec::new(),
+            highlighted: false,
+            language: "",
+            annotations: Vec::new(),
+            module_links: Vec::new(),
+        }
+    }
+
+    // A block of nothing but prose, like the header of a file
+    fn push_prose(&mut self, context: BlockContext) -> Result<()> {
+        self.output.push_str(&self.templates.render("block_before.html", &context)?);
+        self.output.push_str(&self.templates.render("block_after.html", &context)?);
+        self.section += 1;
+        Ok(())
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn document_start(&mut self) -> Result<()> {
+        let page = self.page()?;
+        self.output = self.templates.render("head.html", &page)?;
+        self.page = Some(page);
+        Ok(())
+    }
+
+    fn file_start(&mut self, file: &SourceFile) -> Result<()> {
+        self.file = file.path.to_string_lossy().into_owned();
+        if let Some(intro) = &file.intro {
+            let anchor = unique_anchor(&mut self.anchors, format!("section-{}", self.section));
+            let comment = self.prose(intro);
+            let context = self.context("intro", anchor, comment);
+            self.push_prose(context)?;
+        }
+
+        let title = file.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let dir = file.path.parent().map(|d| d.to_string_lossy()).unwrap_or_default();
+        let anchor = unique_anchor(&mut self.anchors, file_anchor(&file.path));
+        if self.options.with_toc {
+            self.toc.push(TocEntry { href: format!("#{}", anchor), title: escape(&self.file), level: 0 });
+        }
+        let context = self.context("file", anchor, markdown(&format!("**`{}`** (in `{}`)", title, dir)));
+        self.push_prose(context)
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        // items get anchors after their path, everything else is numbered
+        let (anchor, heading) = match &block.item {
+            Some(item) => {
+                let kind = format!("{} {}", item.visibility, item.kind.as_str());
+                (item.anchor(), self.templates.render("item_heading.html",
+                    minijinja::context!(kind => kind.trim(), path => escape(&item.path)))?)
+            }
+            None => (format!("section-{}", self.section), String::new()),
+        };
+        let anchor = unique_anchor(&mut self.anchors, anchor);
+        let comment = self.prose(&block.comment.join("\n"));
+
+        let mut code: Vec<&str> = block.code.iter().map(|l| l.as_str()).collect();
+        let mut annotations = Vec::new();
+        if self.options.with_annotations {
+            for annotation in block.annotations.iter() {
+                let line = &block.code[annotation.line];
+                code[annotation.line] = line[..annotation.column].trim_end();
+                annotations.push(AnnotationContext {
+                    line: block.code_lines[annotation.line],
+                    top: (annotation.line as f32 * 1.5).to_string(),
+                    note: markdown(&annotation.comment),
+                });
+            }
+        }
+        let module_links = block.module_links.iter()
+            .map(|(line, declared)| {
+                let anchor = file_anchor(declared);
+                let page = self.site.as_ref()
+                    .and_then(|site| Some(format!("{}{}", site.root, site.pages.get(declared)?)));
+                ModuleLinkContext {
+                    top: (*line as f32 * 1.5).to_string(),
+                    href: format!("{}#{}", page.unwrap_or_default(), anchor),
+                    anchor,
+                }
+            })
+            .collect();
+        let highlighted = self.options.with_highlighting && *block.language == RUST;
+        let context = BlockContext {
+            heading,
+            start_line: Some(block.starting_line),
+            end_line: Some(block.end_line),
+            lines: code.len(),
+            line_numbers: block.code_lines.clone(),
+            highlighted,
+            code: if highlighted { highlight(&code) } else { code.join("\n").replace("<", "&lt;") },
+            language: block.language.name,
+            annotations,
+            module_links,
+            ..self.context("block", anchor, comment)
+        };
+        self.section += 1;
+
+        self.output.push_str(&self.templates.render("block_before.html", &context)?);
+        self.block = Some(context);
+        Ok(())
+    }
+
+    fn code(&mut self, _block: &Block) -> Result<()> {
+        let context = self.block.as_ref().expect("`block` comes before `code`");
+        self.output.push_str(&self.templates.render("block_code.html", context)?);
+        Ok(())
+    }
+
+    fn block_end(&mut self, _block: &Block) -> Result<()> {
+        let context = self.block.take().expect("`block` comes before `block_end`");
+        self.output.push_str(&self.templates.render("block_after.html", &context)?);
+        Ok(())
+    }
+
+    fn document_end(&mut self) -> Result<String> {
+        let mut page = self.page.take().expect("`document_start` comes first");
+        page.toc = std::mem::take(&mut self.toc);
+        self.output.push_str(&self.templates.render("foot.html", &page)?);
+        Ok(std::mem::take(&mut self.output))
+    }
+}
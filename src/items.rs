@@ -0,0 +1,248 @@
+// To know which item a block documents, we parse the whole file with `syn`
+// and collect every item along with its full path and the line its
+// signature starts on. Blocks are then matched against those lines.
+use std::fmt;
+use std::path::Path;
+
+use syn::spanned::Spanned;
+
+/// The Rust item a block documents
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    /// Full path of the item, e.g. `crate::net::Client::connect`
+    pub path: String,
+    /// What kind of item this is
+    pub kind: ItemKind,
+    /// Visibility the item is declared with
+    pub visibility: Visibility,
+    /// The (1-based) line the item's signature starts on
+    pub line: usize,
+}
+
+/// Kinds of items we know about
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemKind {
+    Const,
+    Enum,
+    ExternCrate,
+    Fn,
+    Impl,
+    Macro,
+    Mod,
+    Static,
+    Struct,
+    Trait,
+    TraitAlias,
+    Type,
+    Union,
+}
+
+/// Visibility of an item
+#[derive(Clone, Debug, PartialEq)]
+pub enum Visibility {
+    /// `pub`
+    Public,
+    /// `pub(crate)`
+    Crate,
+    /// `pub(super)`, `pub(in some::path)` with the given path
+    Restricted(String),
+    /// No visibility given
+    Private,
+}
+
+impl ItemKind {
+    /// The keyword-ish name of the kind, as used in anchors
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemKind::Const => "const",
+            ItemKind::Enum => "enum",
+            ItemKind::ExternCrate => "extern crate",
+            ItemKind::Fn => "fn",
+            ItemKind::Impl => "impl",
+            ItemKind::Macro => "macro",
+            ItemKind::Mod => "mod",
+            ItemKind::Static => "static",
+            ItemKind::Struct => "struct",
+            ItemKind::Trait => "trait",
+            ItemKind::TraitAlias => "trait alias",
+            ItemKind::Type => "type",
+            ItemKind::Union => "union",
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "pub"),
+            Visibility::Crate => write!(f, "pub(crate)"),
+            Visibility::Restricted(path) if path == "super" || path == "self" =>
+                write!(f, "pub({})", path),
+            Visibility::Restricted(path) => write!(f, "pub(in {})", path),
+            Visibility::Private => Ok(()),
+        }
+    }
+}
+
+impl Item {
+    /// An anchor for the item, unique within its file unless the same path
+    /// is defined more than once (e.g. several `impl` blocks).
+    pub fn anchor(&self) -> String {
+        let path: String = self.path.replace("::", ".").chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' || c == '.' { c } else { '-' })
+            .collect();
+        format!("{}.{}", self.kind.as_str().replace(' ', "-"), path)
+    }
+}
+
+// The module path of a source file by convention: `src/lib.rs` is the crate
+// root, `src/net/mod.rs` and `src/net.rs` are `crate::net`. Files outside of
+// `src` are assumed to live next to their crate root.
+pub(crate) fn module_path_for(name: &str) -> String {
+    let path = Path::new(name);
+    let parts: Vec<&str> = path.iter().filter_map(|p| p.to_str()).collect();
+    let mut module: Vec<&str> = match parts.iter().rposition(|p| *p == "src") {
+        Some(idx) => parts[idx + 1..].to_vec(),
+        None => parts.last().into_iter().copied().collect(),
+    };
+    if let Some(last) = module.pop() {
+        let stem = Path::new(last).file_stem().and_then(|s| s.to_str()).unwrap_or(last);
+        if !matches!(stem, "lib" | "main" | "mod") {
+            module.push(stem);
+        }
+    }
+    // binaries are crate roots of their own
+    if module.first() == Some(&"bin") {
+        module.clear();
+    }
+    let mut full = vec!["crate"];
+    full.extend(module);
+    full.join("::")
+}
+
+fn visibility(vis: &syn::Visibility) -> Visibility {
+    match vis {
+        syn::Visibility::Public(_) => Visibility::Public,
+        syn::Visibility::Restricted(r) => {
+            let path: Vec<String> = r.path.segments.iter().map(|s| s.ident.to_string()).collect();
+            match path.join("::").as_str() {
+                "crate" => Visibility::Crate,
+                other => Visibility::Restricted(other.to_string()),
+            }
+        }
+        syn::Visibility::Inherited => Visibility::Private,
+    }
+}
+
+// The name we file an `impl` block's items under: its self type
+fn type_name(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(p) => p.path.segments.last()
+            .map(|s| s.ident.to_string())
+            .unwrap_or_default(),
+        syn::Type::Reference(r) => type_name(&r.elem),
+        syn::Type::Paren(p) => type_name(&p.elem),
+        _ => "_".to_string(),
+    }
+}
+
+struct Collector {
+    items: Vec<Item>,
+}
+
+impl Collector {
+    fn push<S: Spanned>(&mut self, path: String, kind: ItemKind, vis: &syn::Visibility, at: &S) {
+        self.items.push(Item {
+            path,
+            kind,
+            visibility: visibility(vis),
+            line: at.span().start().line,
+        });
+    }
+
+    fn items(&mut self, items: &[syn::Item], parent: &str) {
+        let inherited = syn::Visibility::Inherited;
+        for item in items {
+            let child = |ident: &syn::Ident| format!("{}::{}", parent, ident);
+            match item {
+                syn::Item::Const(i) => self.push(child(&i.ident), ItemKind::Const, &i.vis, &i.const_token),
+                syn::Item::Enum(i) => self.push(child(&i.ident), ItemKind::Enum, &i.vis, &i.enum_token),
+                syn::Item::ExternCrate(i) =>
+                    self.push(child(&i.ident), ItemKind::ExternCrate, &i.vis, &i.extern_token),
+                syn::Item::Fn(i) => self.push(child(&i.sig.ident), ItemKind::Fn, &i.vis, &i.sig.fn_token),
+                syn::Item::ForeignMod(i) => {
+                    for foreign in i.items.iter() {
+                        match foreign {
+                            syn::ForeignItem::Fn(f) =>
+                                self.push(child(&f.sig.ident), ItemKind::Fn, &f.vis, &f.sig.fn_token),
+                            syn::ForeignItem::Static(s) =>
+                                self.push(child(&s.ident), ItemKind::Static, &s.vis, &s.static_token),
+                            syn::ForeignItem::Type(t) =>
+                                self.push(child(&t.ident), ItemKind::Type, &t.vis, &t.type_token),
+                            _ => {}
+                        }
+                    }
+                }
+                syn::Item::Impl(i) => {
+                    let path = format!("{}::{}", parent, type_name(&i.self_ty));
+                    self.push(path.clone(), ItemKind::Impl, &inherited, &i.impl_token);
+                    for impl_item in i.items.iter() {
+                        let member = |ident: &syn::Ident| format!("{}::{}", path, ident);
+                        match impl_item {
+                            syn::ImplItem::Const(c) =>
+                                self.push(member(&c.ident), ItemKind::Const, &c.vis, &c.const_token),
+                            syn::ImplItem::Fn(f) =>
+                                self.push(member(&f.sig.ident), ItemKind::Fn, &f.vis, &f.sig.fn_token),
+                            syn::ImplItem::Type(t) =>
+                                self.push(member(&t.ident), ItemKind::Type, &t.vis, &t.type_token),
+                            _ => {}
+                        }
+                    }
+                }
+                syn::Item::Macro(i) => {
+                    if let Some(ident) = &i.ident {
+                        self.push(child(ident), ItemKind::Macro, &inherited, &i.mac.path);
+                    }
+                }
+                syn::Item::Mod(i) => {
+                    self.push(child(&i.ident), ItemKind::Mod, &i.vis, &i.mod_token);
+                    if let Some((_, content)) = &i.content {
+                        self.items(content, &child(&i.ident));
+                    }
+                }
+                syn::Item::Static(i) => self.push(child(&i.ident), ItemKind::Static, &i.vis, &i.static_token),
+                syn::Item::Struct(i) => self.push(child(&i.ident), ItemKind::Struct, &i.vis, &i.struct_token),
+                syn::Item::Trait(i) => {
+                    self.push(child(&i.ident), ItemKind::Trait, &i.vis, &i.trait_token);
+                    let path = child(&i.ident);
+                    for trait_item in i.items.iter() {
+                        let member = |ident: &syn::Ident| format!("{}::{}", path, ident);
+                        match trait_item {
+                            syn::TraitItem::Const(c) =>
+                                self.push(member(&c.ident), ItemKind::Const, &inherited, &c.const_token),
+                            syn::TraitItem::Fn(f) =>
+                                self.push(member(&f.sig.ident), ItemKind::Fn, &inherited, &f.sig.fn_token),
+                            syn::TraitItem::Type(t) =>
+                                self.push(member(&t.ident), ItemKind::Type, &inherited, &t.type_token),
+                            _ => {}
+                        }
+                    }
+                }
+                syn::Item::TraitAlias(i) =>
+                    self.push(child(&i.ident), ItemKind::TraitAlias, &i.vis, &i.trait_token),
+                syn::Item::Type(i) => self.push(child(&i.ident), ItemKind::Type, &i.vis, &i.type_token),
+                syn::Item::Union(i) => self.push(child(&i.ident), ItemKind::Union, &i.vis, &i.union_token),
+                _ => {}
+            }
+        }
+    }
+}
+
+// All items of the file `source`, which is the module at `module_path`, in
+// the order they appear in.
+pub(crate) fn collect(source: &str, module_path: &str) -> syn::Result<Vec<Item>> {
+    let file = syn::parse_file(source)?;
+    let mut collector = Collector { items: Vec::new() };
+    collector.items(&file.items, module_path);
+    Ok(collector.items)
+}
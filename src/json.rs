@@ -0,0 +1,98 @@
+//! The blocks of the rendered files as JSON, for tools of your own to build
+//! on. The format is versioned: fields may be added within a version, but are
+//! never renamed or removed.
+use pulldown_cmark::{Parser, html};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::{CommentKind, Renderer};
+
+/// The version of the format we write
+pub const VERSION: u32 = 1;
+
+/// All files of a run, in the order they were given
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    /// Version of the format, see [`VERSION`](constant.VERSION.html)
+    pub version: u32,
+    pub files: Vec<File>,
+}
+
+/// A source file and the blocks it was split into
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct File {
+    /// Path of the file as it was given
+    pub path: String,
+    pub blocks: Vec<Block>,
+}
+
+/// One block of prose and the code it explains
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    /// The prose as Markdown, without comment markers
+    pub comment: String,
+    /// The prose rendered to HTML
+    pub comment_html: String,
+    /// The code, lines separated by `\n`
+    pub code: String,
+    /// The (1-based) line the block starts on
+    pub start_line: usize,
+    /// The (1-based) last line of the block
+    pub end_line: usize,
+    /// The kind of comment the prose was written as, if there is any
+    pub comment_kind: Option<CommentKind>,
+    /// Name of the language of the code
+    pub language: String,
+}
+
+impl Document {
+    /// The document for the blocks of `files`
+    pub fn from_files<I: IntoIterator<Item=crate::SourceFile>>(files: I) -> Document {
+        let files = files.into_iter().map(|file| File {
+            path: file.path.to_string_lossy().into_owned(),
+            blocks: file.blocks.iter().map(Block::from).collect(),
+        }).collect();
+        Document { version: VERSION, files }
+    }
+}
+
+impl From<&crate::Block> for Block {
+    fn from(block: &crate::Block) -> Block {
+        let comment = block.comment().join("\n");
+        let mut comment_html = String::new();
+        html::push_html(&mut comment_html, Parser::new(&comment));
+        Block {
+            comment,
+            comment_html,
+            code: block.code().join("\n"),
+            start_line: block.start_line(),
+            end_line: block.end_line(),
+            comment_kind: block.comment_kind(),
+            language: block.language().name.to_string(),
+        }
+    }
+}
+
+/// Renders files into a [`Document`](struct.Document.html), pretty-printed
+#[derive(Default)]
+pub struct JsonRenderer {
+    files: Vec<File>,
+}
+
+impl Renderer for JsonRenderer {
+    fn file_start(&mut self, file: &crate::SourceFile) -> Result<()> {
+        self.files.push(File { path: file.path.to_string_lossy().into_owned(), blocks: Vec::new() });
+        Ok(())
+    }
+
+    fn block(&mut self, block: &crate::Block) -> Result<()> {
+        let file = self.files.last_mut().expect("`file_start` comes before `block`");
+        file.blocks.push(Block::from(block));
+        Ok(())
+    }
+
+    fn document_end(&mut self) -> Result<String> {
+        let document = Document { version: VERSION, files: std::mem::take(&mut self.files) };
+        Ok(serde_json::to_string_pretty(&document).expect("The document always serializes"))
+    }
+}
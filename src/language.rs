@@ -0,0 +1,157 @@
+use std::path::Path;
+
+/// Comment and literal syntax of a language we can extract blocks from
+#[derive(Debug, PartialEq)]
+pub struct Language {
+    /// Name of the language, also used as Prism's `language-` class
+    pub name: &'static str,
+    /// File extensions, without the leading dot
+    pub extensions: &'static [&'static str],
+    /// Marker starting a comment running to the end of the line
+    pub line_comment: Option<&'static str>,
+    /// Markers opening and closing a block comment
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Whether block comments may be nested, like in Rust
+    pub nested_comments: bool,
+    /// Characters delimiting string literals
+    pub quotes: &'static [char],
+    /// Those of the `quotes` delimiting strings without escapes, like `'` in
+    /// shell, where a `\` is just a `\`
+    pub verbatim_quotes: &'static [char],
+    /// Whether to lex raw strings, char literals and lifetimes the Rust way
+    pub rust_literals: bool,
+    /// Whether comment markers only count at the start of a line or after
+    /// whitespace, like `#` in shell
+    pub comments_start_words: bool,
+}
+
+/// The language we assume if no other can be detected
+pub static RUST: Language = Language {
+    name: "rust",
+    extensions: &["rs"],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    nested_comments: true,
+    quotes: &['"'],
+    verbatim_quotes: &[],
+    rust_literals: true,
+    comments_start_words: false,
+};
+
+// All the languages we know about, looked up by name and extension.
+static LANGUAGES: &[&Language] = &[
+    &RUST,
+    &Language {
+        name: "toml",
+        extensions: &["toml"],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &['\''],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "bash",
+        extensions: &["sh", "bash", "zsh"],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &['\''],
+        rust_literals: false,
+        comments_start_words: true,
+    },
+    &Language {
+        name: "python",
+        extensions: &["py"],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "sql",
+        extensions: &["sql"],
+        line_comment: Some("--"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "c",
+        extensions: &["c", "h"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "cpp",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\''],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\'', '`'],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+    &Language {
+        name: "typescript",
+        extensions: &["ts"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\'', '`'],
+        verbatim_quotes: &[],
+        rust_literals: false,
+        comments_start_words: false,
+    },
+];
+
+impl Language {
+    /// All known languages
+    pub fn all() -> &'static [&'static Language] {
+        LANGUAGES
+    }
+
+    /// Find a language by its name
+    pub fn by_name(name: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().copied().find(|l| l.name == name)
+    }
+
+    /// Find a language by file extension
+    pub fn by_extension(ext: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().copied().find(|l| l.extensions.contains(&ext))
+    }
+
+    /// Detect the language from the extension of `path`, falling back to Rust
+    pub fn for_path<P: AsRef<Path>>(path: P) -> &'static Language {
+        path.as_ref().extension()
+            .and_then(|e| e.to_str())
+            .and_then(Language::by_extension)
+            .unwrap_or(&RUST)
+    }
+}
@@ -0,0 +1,255 @@
+// A small Rust lexer, just good enough to tell comments apart from code.
+//
+// Looking at each line on its own isn't enough for that: a line within a
+// multi-line string may very well start with `//`, and a `/*` inside a raw
+// string doesn't open any comment. So we go through the source character by
+// character and keep track of which literal or comment we are in, carrying
+// that state over from one line to the next.
+//
+// Which markers start comments and strings depends on the `Language`. All of
+// them are plain ASCII, so we can safely skip over them byte by byte.
+use crate::CommentType;
+use crate::language::Language;
+
+// The pieces a single line is split into.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token<'a> {
+    // Anything that isn't a comment or literal.
+    Code(&'a str),
+    // A string or char literal, or the part of it found on this line.
+    Literal(&'a str),
+    // A `//`-comment, running until the end of the line.
+    LineComment(CommentType, &'a str),
+    // The part of a `/* */`-comment found on this line. `opens` and `closes`
+    // tell whether the comment starts and ends on this line respectively.
+    BlockComment { kind: CommentType, text: &'a str, opens: bool, closes: bool },
+}
+
+impl<'a> Token<'a> {
+    // The source text this token was lexed from.
+    pub(crate) fn text(&self) -> &'a str {
+        match *self {
+            Token::Code(text) | Token::Literal(text) | Token::LineComment(_, text) => text,
+            Token::BlockComment { text, .. } => text,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    Str(char),
+    RawStr(usize),
+    BlockComment(CommentType, usize),
+}
+
+pub(crate) struct Lexer {
+    language: &'static Language,
+    state: State,
+}
+
+pub(crate) fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Lexer {
+    pub(crate) fn new(language: &'static Language) -> Lexer {
+        Lexer { language, state: State::Code }
+    }
+
+    // Whether the next line starts out as code, rather than within a
+    // literal or block comment.
+    pub(crate) fn at_code(&self) -> bool {
+        self.state == State::Code
+    }
+
+    // Split the next line (without its line ending) into tokens.
+    pub(crate) fn line<'a>(&mut self, line: &'a str) -> Vec<Token<'a>> {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let at = |i: usize| chars.get(i).map(|c| c.1);
+        let pos = |i: usize| chars.get(i).map(|c| c.0).unwrap_or(line.len());
+        let push_code = |tokens: &mut Vec<Token<'a>>, start: usize, end: usize| {
+            if start < end {
+                tokens.push(Token::Code(&line[start..end]));
+            }
+        };
+        // the index of the quote closing a char literal opened at `i`
+        let char_end = |i: usize| {
+            if at(i + 1) == Some('\\') {
+                (i + 3..chars.len()).find(|j| chars[*j].1 == '\'')
+            } else if at(i + 2) == Some('\'') {
+                Some(i + 2)
+            } else {
+                None
+            }
+        };
+
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        let mut opens = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i].1;
+            match self.state {
+                State::Code => {
+                    let rest = &line[pos(i)..];
+                    // in shell, `${#array[@]}` or `url#fragment` are no comments
+                    let word_start = !self.language.comments_start_words
+                        || i == 0 || chars[i - 1].1.is_whitespace();
+                    let block_comment = self.language.block_comment
+                        .filter(|(open, _)| word_start && rest.starts_with(open));
+                    if let Some(marker) = self.language.line_comment
+                            .filter(|m| word_start && rest.starts_with(m)) {
+                        push_code(&mut tokens, start, pos(i));
+                        let kind = if marker != "//" {
+                            CommentType::Simple
+                        } else if rest.starts_with("///") && !rest.starts_with("////") {
+                            CommentType::Doc
+                        } else if rest.starts_with("//!") || rest.starts_with("// !") {
+                            CommentType::Bang
+                        } else {
+                            CommentType::Simple
+                        };
+                        tokens.push(Token::LineComment(kind, rest));
+                        return tokens;
+                    } else if let Some((open, _)) = block_comment {
+                        push_code(&mut tokens, start, pos(i));
+                        let after = &rest[open.len()..];
+                        let kind = if after.starts_with('!') {
+                            CommentType::BlockBang
+                        } else if after.starts_with('*') && !after.starts_with("**")
+                                && !after.starts_with("*/") {
+                            CommentType::BlockDoc
+                        } else {
+                            CommentType::Block
+                        };
+                        self.state = State::BlockComment(kind, 1);
+                        start = pos(i);
+                        opens = true;
+                        i += open.len();
+                        continue;
+                    } else if self.language.quotes.contains(&c) {
+                        push_code(&mut tokens, start, pos(i));
+                        start = pos(i);
+                        self.state = State::Str(c);
+                    } else if c == '\'' && self.language.rust_literals {
+                        // either a char literal or a lifetime / label
+                        if let Some(end) = char_end(i) {
+                            push_code(&mut tokens, start, pos(i));
+                            tokens.push(Token::Literal(&line[pos(i)..pos(end + 1)]));
+                            start = pos(end + 1);
+                            i = end;
+                        }
+                    } else if is_ident(c) {
+                        // read the whole word, so that e.g. the `r` in `for`
+                        // isn't mistaken for the start of a raw string
+                        let mut end = i + 1;
+                        while at(end).map(is_ident).unwrap_or(false) {
+                            end += 1;
+                        }
+                        let word = &line[pos(i)..pos(end)];
+                        let mut hashes = 0;
+                        while at(end + hashes) == Some('#') {
+                            hashes += 1;
+                        }
+                        let rust = self.language.rust_literals;
+                        let byte_char = if rust && word == "b" && at(end) == Some('\'') {
+                            char_end(end)
+                        } else {
+                            None
+                        };
+                        if rust && matches!(word, "r" | "br" | "cr") && at(end + hashes) == Some('"') {
+                            // a raw string: `r"..."`, `r#"..."#`, `br"..."`
+                            push_code(&mut tokens, start, pos(i));
+                            start = pos(i);
+                            self.state = State::RawStr(hashes);
+                            i = end + hashes;
+                        } else if rust && matches!(word, "b" | "c") && at(end) == Some('"') {
+                            push_code(&mut tokens, start, pos(i));
+                            start = pos(i);
+                            self.state = State::Str('"');
+                            i = end;
+                        } else if let Some(closing) = byte_char {
+                            push_code(&mut tokens, start, pos(i));
+                            tokens.push(Token::Literal(&line[pos(i)..pos(closing + 1)]));
+                            start = pos(closing + 1);
+                            i = closing;
+                        } else {
+                            i = end - 1;
+                        }
+                    }
+                }
+                State::Str(quote) => {
+                    if c == '\\' && !self.language.verbatim_quotes.contains(&quote) {
+                        i += 1;
+                    } else if c == quote {
+                        tokens.push(Token::Literal(&line[start..pos(i + 1)]));
+                        start = pos(i + 1);
+                        self.state = State::Code;
+                    }
+                }
+                State::RawStr(hashes) => {
+                    if c == '"' && (1..=hashes).all(|h| at(i + h) == Some('#')) {
+                        i += hashes;
+                        tokens.push(Token::Literal(&line[start..pos(i + 1)]));
+                        start = pos(i + 1);
+                        self.state = State::Code;
+                    }
+                }
+                State::BlockComment(kind, depth) => {
+                    let (open, close) = self.language.block_comment
+                        .expect("only languages with block comments get here");
+                    let rest = &line[pos(i)..];
+                    if self.language.nested_comments && rest.starts_with(open) {
+                        self.state = State::BlockComment(kind, depth + 1);
+                        i += open.len() - 1;
+                    } else if rest.starts_with(close) {
+                        i += close.len() - 1;
+                        if depth > 1 {
+                            self.state = State::BlockComment(kind, depth - 1);
+                        } else {
+                            tokens.push(Token::BlockComment {
+                                kind,
+                                text: &line[start..pos(i + 1)],
+                                opens,
+                                closes: true,
+                            });
+                            self.state = State::Code;
+                            start = pos(i + 1);
+                            opens = false;
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        if start < line.len() {
+            tokens.push(match self.state {
+                State::BlockComment(kind, _) => Token::BlockComment {
+                    kind,
+                    text: &line[start..],
+                    opens,
+                    closes: false,
+                },
+                State::Str(_) | State::RawStr(_) => Token::Literal(&line[start..]),
+                State::Code => Token::Code(&line[start..]),
+            });
+        } else if let State::BlockComment(kind, _) = self.state {
+            // an empty line within a block comment
+            tokens.push(Token::BlockComment { kind, text: "", opens, closes: false });
+        }
+        tokens
+    }
+}
+
+// How many more braces these tokens open than they close, ignoring those in
+// literals and comments.
+pub(crate) fn brace_balance(tokens: &[Token]) -> i32 {
+    tokens.iter()
+        .filter_map(|t| match t { Token::Code(code) => Some(code), _ => None })
+        .flat_map(|code| code.chars())
+        .map(|c| match c { '{' => 1, '}' => -1, _ => 0 })
+        .sum()
+}
@@ -1,20 +1,176 @@
 
-use std::fs::File;
-use std::path::Path;
-use std::io::{BufRead, Read, BufReader};
+mod attribute;
+pub mod build;
+mod directive;
+mod error;
+mod highlight;
+mod html;
+mod items;
+pub mod json;
+mod language;
+mod lexer;
+mod manifest;
+mod modules;
+mod render;
+mod segment;
+mod site;
+mod template;
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::io::{self, BufRead, Read, BufReader};
 use std::iter::IntoIterator;
 use std::cmp::PartialEq;
-use pulldown_cmark::{Parser, html};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+use attribute::DocValue;
+use directive::Directive;
+use lexer::{Lexer, Token};
+
+pub use error::{Error, Result, Warning};
+pub use html::HtmlRenderer;
+pub use items::{Item, ItemKind, Visibility};
+pub use language::Language;
+pub use json::JsonRenderer;
+pub use manifest::{Config, Package, Target, TargetKind, default_packages, find_manifest, read_manifest,
+    workspace_root};
+pub use render::{Renderer, render};
+pub use segment::{DefaultSegmenter, Segment, Segmenter};
+pub use site::build_site;
 
 // `Block` stores code sections, consisting of comments and associated code.
 // We initialise a new block with empty `Vec` which will later be joined.
+#[derive(Clone, Debug)]
 pub struct Block {
     comment: Vec<String>,
     code: Vec<String>,
+    annotations: Vec<Annotation>,
     starting_line: usize,
+    // the line each line of `code` is found on, with gaps where lines were
+    // skipped or hidden
+    code_lines: Vec<usize>,
+    // the last line that went into the block
+    end_line: usize,
+    // the kind of the comments in the block, which is the same for all
+    kind: Option<CommentType>,
+    // the file the block was extracted from, shared by all its blocks
+    path: Arc<Path>,
+    language: &'static Language,
+    item: Option<Item>,
+    // `mod foo;` declarations in `code`, by line, and the file they
+    // declare
+    module_links: Vec<(usize, PathBuf)>,
+    // the source lines the block was made from, exactly as they were read,
+    // including skipped and hidden ones
+    raw: Vec<String>,
+}
+
+/// A source file and the blocks it was split into
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    /// Path of the file, as it is shown in the header
+    pub path: PathBuf,
+    /// The blocks of the file, in order
+    pub blocks: Vec<Block>,
+    /// Markdown to show before the file, like the heading of the target it
+    /// is the first file of
+    pub intro: Option<String>,
+}
+
+// An `Annotation` is a comment trailing code on the same line. We keep the
+// code line as it is and remember where the comment starts within it, so the
+// renderer may choose to show it in the margin instead.
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    line: usize,
+    column: usize,
+    comment: String,
+}
+
+impl Annotation {
+    /// The (0-based) line of the block's code the comment trails
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The byte offset within the code line the comment starts at
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The comment, without its markers
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+}
+
+impl SourceFile {
+    pub fn new<P: Into<PathBuf>>(path: P, blocks: Vec<Block>) -> SourceFile {
+        SourceFile { path: path.into(), blocks, intro: None }
+    }
+}
+
+/// Handler for the warnings found during extraction
+pub type OnWarning = Box<dyn Fn(&Warning)>;
+
+/// Extraction Options
+#[derive(Default)]
+pub struct ExtractOptions {
+    /// Language to read the source as, detected by file extension if `None`
+    pub language: Option<&'static Language>,
+    /// Called with every warning, printed to stderr if `None`
+    pub on_warning: Option<OnWarning>,
+    /// Which comments become prose, splitting up the code
+    pub prose: Prose,
+    /// Module path of the file, e.g. `crate::net`, guessed from its path if `None`
+    pub module_path: Option<String>,
+    /// Whether to decode sources that aren't valid UTF-8 anyway, replacing
+    /// what can't be decoded with `�` and warning about it, rather than failing
+    pub lossy: bool,
+    /// Where to split blocks, [`DefaultSegmenter`](struct.DefaultSegmenter.html) if `None`
+    pub segmenter: Option<Box<dyn Segmenter>>,
+}
+
+/// Which comments are rendered as prose, rather than kept with the code
+#[derive(Clone, Copy, Debug, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Prose {
+    /// Every comment
+    #[default]
+    All,
+    /// Only doc comments, `///`, `//!`, `/** */`, `/*! */` and `#[doc]`
+    Doc,
+    /// Only comments outside of any braces, so function bodies stay in one piece
+    TopLevel,
+}
+
+impl FromStr for Prose {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Prose, String> {
+        match s {
+            "all" => Ok(Prose::All),
+            "doc" => Ok(Prose::Doc),
+            "top-level" => Ok(Prose::TopLevel),
+            _ => Err(format!("unknown prose mode `{}`", s)),
+        }
+    }
+}
+
+impl ExtractOptions {
+    fn warn(&self, path: &str, line: usize, message: String) {
+        let warning = Warning { path: path.into(), line, message };
+        match &self.on_warning {
+            Some(on_warning) => on_warning(&warning),
+            None => eprintln!("warning: {}", warning),
+        }
+    }
 }
 
 /// Rendering Options
+#[derive(Clone)]
 pub struct Options {
     /// HTML title to include
     pub title: String,
@@ -28,164 +184,632 @@ pub struct Options {
     pub extra_header: Option<String>,
     /// Filepath with extra for footer
     pub extra_footer: Option<String>,
+    /// Whether to render end-of-line comments as margin annotations
+    pub with_annotations: bool,
+    /// Directory with templates to use instead of the built-in ones
+    pub template_dir: Option<String>,
+    /// Whether to add a menu listing the files and the headings of their prose
+    pub with_toc: bool,
+    /// Whether to highlight Rust code while rendering, rather than leave it to
+    /// the scripts
+    pub with_highlighting: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            title: String::new(),
+            with_css: true,
+            with_js: true,
+            extra_meta: None,
+            extra_header: None,
+            extra_footer: None,
+            with_annotations: true,
+            template_dir: None,
+            with_toc: true,
+            with_highlighting: true,
+        }
+    }
 }
 
 impl Block {
-    pub fn new(starting_line: usize) -> Block {
-        return Block {
+    pub fn new(starting_line: usize, language: &'static Language) -> Block {
+        Block {
             comment: Vec::new(),
             code: Vec::new(),
+            annotations: Vec::new(),
             starting_line,
+            code_lines: Vec::new(),
+            end_line: starting_line,
+            kind: None,
+            path: Arc::from(Path::new("")),
+            language,
+            item: None,
+            module_links: Vec::new(),
+            raw: Vec::new(),
         }
     }
 
-    pub fn new_file(title: &str, path: &str) -> Block {
-        return Block {
-            comment: vec![format!("**`{:}`** (in `{:}`)", title, path)],
-            code: vec![],
-            starting_line: 0,
-        }
+    pub fn has_code(&self) -> bool {
+        self.code.iter().any(|i| !i.trim().is_empty())
     }
 
-    pub fn has_code(&self) -> bool {
-        if self.code.len() == 0 { return false }
-        self.code.iter().find(|i| i.trim().len() > 0).is_some()
+    /// The prose, as lines of Markdown without comment markers
+    pub fn comment(&self) -> &[String] {
+        &self.comment
+    }
+
+    /// The lines of code
+    pub fn code(&self) -> &[String] {
+        &self.code
+    }
+
+    /// The (1-based) line the block starts on
+    pub fn start_line(&self) -> usize {
+        self.starting_line
+    }
+
+    /// The (1-based) line of the first line of code, 0 if there is none
+    pub fn code_line(&self) -> usize {
+        self.code_lines.first().copied().unwrap_or(0)
+    }
+
+    /// The (1-based) line each line of code is found on. Lines left out by
+    /// directives leave gaps.
+    pub fn code_lines(&self) -> &[usize] {
+        &self.code_lines
+    }
+
+    /// The (1-based) last line of the block
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    /// The file the block was extracted from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The kind of comment the prose was written as, `None` without prose
+    pub fn comment_kind(&self) -> Option<CommentKind> {
+        self.kind.map(CommentType::kind)
+    }
+
+    /// The language of the code
+    pub fn language(&self) -> &'static Language {
+        self.language
+    }
+
+    /// Comments trailing lines of the code
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// The lines of the source the block was made from, as they were read,
+    /// line endings included. This includes lines left out of the block's
+    /// comment and code, like directives and skipped parts.
+    pub fn raw(&self) -> &[String] {
+        &self.raw
+    }
+
+    /// The item whose definition starts within this block's code, if any
+    pub fn item(&self) -> Option<&Item> {
+        self.item.as_ref()
+    }
+
+    // Comment lines come with whatever whitespace followed the comment
+    // marker. We remove the indentation all of them have in common (and any
+    // trailing whitespace), but keep the relative one, as it is meaningful to
+    // Markdown (nested lists, indented code). Whitespace counts as common
+    // only if it is the same, be it spaces, tabs or any other.
+    fn dedent_comment(&mut self) {
+        let indent = self.comment.iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| &l[..l.len() - l.trim_start().len()])
+            .reduce(|indent, other| {
+                let shared = indent.chars().zip(other.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(c, _)| c.len_utf8())
+                    .sum();
+                &indent[..shared]
+            })
+            .unwrap_or("")
+            .to_string();
+        for line in self.comment.iter_mut() {
+            *line = if line.trim().is_empty() {
+                String::new()
+            } else {
+                line.strip_prefix(indent.as_str()).unwrap_or(line).trim_end().to_string()
+            };
+        }
     }
 }
 
-#[derive(PartialEq)]
+/// The kind of comment a block's prose was written as
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentKind {
+    /// `// ...`, or a line comment of another language
+    Line,
+    /// `/* ... */`
+    Block,
+    /// `/// ...` or `#[doc = "..."]`
+    OuterDoc,
+    /// `//! ...` or `#![doc = "..."]`
+    InnerDoc,
+    /// `/** ... */`
+    OuterBlockDoc,
+    /// `/*! ... */`
+    InnerBlockDoc,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
 enum CommentType {
     Simple,
     Bang,
     Doc,
-    ANY
+    Block,
+    BlockBang,
+    BlockDoc,
+}
+
+impl CommentType {
+    fn is_doc(self) -> bool {
+        matches!(self, CommentType::Bang | CommentType::Doc
+            | CommentType::BlockBang | CommentType::BlockDoc)
+    }
+
+    fn kind(self) -> CommentKind {
+        match self {
+            CommentType::Simple => CommentKind::Line,
+            CommentType::Bang => CommentKind::InnerDoc,
+            CommentType::Doc => CommentKind::OuterDoc,
+            CommentType::Block => CommentKind::Block,
+            CommentType::BlockBang => CommentKind::InnerBlockDoc,
+            CommentType::BlockDoc => CommentKind::OuterBlockDoc,
+        }
+    }
+}
+
+// Block comments may be decorated with a ` * ` gutter on every line, which
+// we do not want to end up in the rendered markdown.
+fn strip_gutter(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    match trimmed.strip_prefix('*') {
+        Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+        None => line,
+    }
+}
+
+// The text of a comment token without its comment markers. `None` if there
+// is nothing worth adding to the prose, like the bare `/**` opening a doc
+// comment.
+fn comment_content<'a>(language: &Language, token: &Token<'a>) -> Option<(CommentType, &'a str)> {
+    match *token {
+        Token::Code(_) | Token::Literal(_) => None,
+        Token::LineComment(kind, text) => {
+            let strip_pos = match kind {
+                CommentType::Doc => 3,
+                CommentType::Bang if text.starts_with("// !") => 4,
+                CommentType::Bang => 3,
+                _ => language.line_comment.map(|m| m.len()).unwrap_or(0),
+            };
+            Some((kind, text.split_at(strip_pos).1))
+        }
+        Token::BlockComment { kind, text, opens, closes } => {
+            let (open, close) = language.block_comment.unwrap_or(("/*", "*/"));
+            let mut content = text;
+            if closes {
+                content = content.strip_suffix(close).unwrap_or(content).trim_end();
+            }
+            if opens {
+                content = &content[open.len() + if kind == CommentType::Block { 0 } else { 1 }..];
+            } else {
+                content = strip_gutter(content);
+            }
+            // keep empty lines within the comment, they separate paragraphs
+            if content.trim().is_empty() && (opens || closes) {
+                None
+            } else {
+                Some((kind, content))
+            }
+        }
+    }
 }
 
 // We divide the source code into code/comment blocks.
 // A `Vec` of `Block`s is returned for further processing.
-pub fn extract(path: String) -> Vec<Block> {
-    let file = File::open(path).expect("Unable to open input file");
-    let mut process_as_code = false;
-    let mut current_comment_type : CommentType = CommentType::ANY;
-    let mut blocks: Vec<Block> = Vec::new();
-    let mut current_block = Block::new(1);
+pub fn extract(path: String, options: &ExtractOptions) -> Result<Vec<Block>> {
+    let file = File::open(&path)
+        .map_err(|source| Error::Io { path: path.clone().into(), source })?;
+    extract_reader(&path, file, options)
+}
 
-    for (idx, line) in BufReader::new(file).lines().into_iter().enumerate() {
+// Same as `extract`, but for sources that aren't on disk, like the output of
+// a code generator. `name` is used to detect the language and report errors.
+pub fn extract_str(name: &str, source: &str, options: &ExtractOptions) -> Result<Vec<Block>> {
+    let lines = source.split_inclusive('\n').map(|l| Ok(l.as_bytes().to_vec()));
+    extract_lines(name, lines, options.module_path.as_deref(), options)
+}
 
-        let line_str = line.unwrap().to_string();
-        let stripped = line_str.trim();
+// Same as `extract`, reading the source from any `reader`, e.g. stdin.
+pub fn extract_reader<R: Read>(name: &str, reader: R, options: &ExtractOptions)
+    -> Result<Vec<Block>>
+{
+    extract_lines(name, raw_lines(BufReader::new(reader)), options.module_path.as_deref(), options)
+}
 
-        if stripped.starts_with("//") {
-            if process_as_code {
-                blocks.push(current_block);
-                current_block = Block::new(idx + 1);
-            }
-            process_as_code = false;
-        } else {
-            process_as_code = true;
-            current_comment_type = CommentType::ANY;
+// The lines of `reader`, unlike `BufRead::lines` keeping their line endings
+// and leaving the decoding to us.
+fn raw_lines<R: BufRead>(mut reader: R) -> impl Iterator<Item=io::Result<Vec<u8>>> {
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line)),
+            Err(err) => Some(Err(err)),
         }
+    })
+}
 
-        if process_as_code {
-            current_block.code.push(line_str.to_string());
-        } else {
-            let (strip_pos, com_type) = {
-                if stripped.starts_with("///") {
-                    (3,  CommentType::Doc)
-                } else if stripped.starts_with("//!") {
-                    (3,  CommentType::Bang)
-                } else if stripped.starts_with("// !") {
-                    (4,  CommentType::Bang)
-                } else {
-                    (2,  CommentType::Simple)
-                }
-            };
-            
-            let line = stripped.split_at(strip_pos).1;
-            if current_comment_type != CommentType::ANY &&
-                    com_type != current_comment_type {
-                // different type of comment, means we assume a new block
-                blocks.push(current_block);
-                current_block = Block::new(idx + 1);
+// Rebuild the source the `blocks` were extracted from, byte for byte.
+pub fn blocks_to_source(blocks: &[Block]) -> String {
+    blocks.iter().flat_map(|b| b.raw.iter()).map(|l| l.as_str()).collect()
+}
+
+// Extract all files of a crate, starting from its root `lib.rs` or `main.rs`
+// and following `mod` declarations depth-first, so files come in the order
+// the crate declares them. The `mod foo;` lines link to the file they declare.
+pub fn extract_crate<P: AsRef<Path>>(root: P, options: &ExtractOptions) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    for file in modules::walk(root.as_ref(), options)? {
+        let name = file.path.to_string_lossy();
+        let source = File::open(&file.path)
+            .map_err(|source| Error::Io { path: file.path.clone(), source })?;
+        let mut blocks = extract_lines(&name, raw_lines(BufReader::new(source)),
+            Some(&file.module_path), options)?;
+        for (line, declared) in file.declarations {
+            let found = blocks.iter_mut()
+                .find_map(|b| Some((b.code_lines.iter().position(|&l| l == line)?, b)));
+            // the declaration may well be hidden
+            if let Some((index, block)) = found {
+                block.module_links.push((index, declared));
             }
-            current_comment_type = com_type;
-            current_block.comment.push(line.trim().to_string());
         }
+        files.push(SourceFile::new(file.path, blocks));
     }
-    blocks.push(current_block);
-    return blocks;
+    Ok(files)
+}
+
+// Extract all targets of the `package`. The first file of each target is
+// introduced by a heading, and the very first by the package's description
+// and authors, too.
+pub fn extract_package(package: &Package, options: &ExtractOptions) -> Result<Vec<SourceFile>> {
+    let mut intro = vec![format!("# {}", package.name)];
+    if let Some(description) = &package.description {
+        intro.push(description.clone());
+    }
+    if !package.authors.is_empty() {
+        intro.push(format!("_by {}_", package.authors.join(", ")));
+    }
+
+    let mut files = Vec::new();
+    for target in package.selected_targets() {
+        let mut target_files = extract_crate(&target.path, options)?;
+        if let Some(first) = target_files.first_mut() {
+            intro.push(format!("## {} `{}`", target.kind.as_str(), target.name));
+            first.intro = Some(intro.join("\n\n"));
+            intro.clear();
+        }
+        files.extend(target_files);
+    }
+    Ok(files)
 }
 
-// Build a full HTML document from a vector of blocks.
-// This function also inlines the CSS.
-pub fn build_html<I: IntoIterator<Item=Block>>(blocks: I, options: Options) -> String {
-    let mut html_output = String::new();
+fn extract_lines<I>(name: &str, lines: I, module_path: Option<&str>, options: &ExtractOptions)
+    -> Result<Vec<Block>>
+    where I: Iterator<Item=io::Result<Vec<u8>>>
+{
+    let language = options.language.unwrap_or_else(|| Language::for_path(name));
+    let mut lexer = Lexer::new(language);
+    let segmenter = options.segmenter.as_deref().unwrap_or(&DefaultSegmenter);
+    // what went into the current block last, `None` while it is empty
+    let mut previous: Option<Segment> = None;
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut current_block = Block::new(1, language);
+    // the line a `skip-start` directive was found on, while we are skipping
+    let mut skipping: Option<usize> = None;
+    // brace depth of the item we are hiding, and whether it started already
+    let mut hiding: Option<(i32, bool)> = None;
+    // brace depth at the end of the current line
+    let mut depth = 0;
+    // whether the block comment the line starts in began as part of the code
+    let mut comment_in_code = false;
+    // the whole source, to parse for items in the end
+    let mut source = String::new();
+    // the previous line, to be kept by the block it ended up in
+    let mut raw: Option<String> = None;
+    // whether we warned about the source not being UTF-8 already
+    let mut decoded_lossily = false;
 
-    let include_static = |file : String, mut target: &mut String| {
-        let path = Path::new(&file);
-        let is_md = if let Some(ext) = path.extension() {
-            match ext.to_str() {
-                Some("md") | Some("mdown") | Some("markdown") => true,
-                _ => false
+    for (idx, line) in lines.enumerate() {
+        current_block.raw.extend(raw.take());
+
+        let line = line.map_err(|source| Error::Io { path: name.into(), source })?;
+        let line = match String::from_utf8(line) {
+            Ok(line) => line,
+            Err(err) if options.lossy => {
+                if !decoded_lossily {
+                    options.warn(name, idx + 1,
+                        "not valid UTF-8, replacing what can't be decoded with `\u{fffd}`".to_string());
+                    decoded_lossily = true;
+                }
+                String::from_utf8_lossy(err.as_bytes()).into_owned()
             }
-        } else {
-            false
+            Err(_) => return Err(Error::Encoding { path: name.into(), line: idx + 1 }),
         };
+        // we work on the line without its ending, `\n` or `\r\n`, and without
+        // the byte order mark some editors start files with
+        let mut line_str = line.strip_suffix('\n')
+            .map(|l| l.strip_suffix('\r').unwrap_or(l))
+            .unwrap_or(&line);
+        if idx == 0 {
+            line_str = line_str.strip_prefix('\u{feff}').unwrap_or(line_str);
+        }
+        let line_str = line_str.to_string();
+        raw = Some(line);
+        source.push_str(&line_str);
+        source.push('\n');
+        let at_code = lexer.at_code();
+        let tokens = lexer.line(&line_str);
+        let line_depth = depth;
+        depth += lexer::brace_balance(&tokens);
 
-        let mut f = File::open(path).expect("File not found");
-        if is_md {
-            let mut source = String::new();
-            f.read_to_string(&mut source).expect("failed  to read file");
-            html::push_html(&mut target, Parser::new(&source));
+        // Only lines holding nothing but comments are prose. The exception is
+        // a line closing a multi-line block comment with code following it,
+        // which we split up into comment and code. A block comment opened
+        // after code stays code until it is closed.
+        let is_code = |t: &Token| match t {
+            Token::Code(c) => !c.trim().is_empty(),
+            Token::Literal(_) => true,
+            _ => false,
+        };
+        let split_at = if comment_in_code
+                || tokens.iter().all(|t| matches!(t, Token::Code(_) | Token::Literal(_))) {
+            0
+        } else if !tokens.iter().any(is_code) {
+            tokens.len()
+        } else if let Some(Token::BlockComment { opens: false, .. }) = tokens.first() {
+            1
         } else {
-            f.read_to_string(&mut target)
-                .expect("failed to read file");
+            0
         };
-    };
+        let (mut comments, mut code) = tokens.split_at(split_at);
 
-    html_output.push_str(&format!(include_str!("static/head.html"), title=options.title));
+        let directive = match comments.last() {
+            Some(token @ Token::LineComment(CommentType::Simple, _)) if code.is_empty()
+                    && comments.iter().filter(|t| !matches!(t, Token::Code(_))).count() == 1 =>
+                comment_content(language, token).and_then(|(_, c)| directive::parse(c)),
+            _ => None,
+        };
 
-    if options.with_css {
-        html_output.push_str("<style>");
-        html_output.push_str(include_str!("static/style.css"));
-        html_output.push_str("</style>");
-    };
+        if skipping.is_some() {
+            if let Some(Ok(Directive::SkipEnd)) = directive {
+                skipping = None;
+            }
+            continue;
+        }
 
-    if options.with_js {
-        html_output.push_str("<script>");
-        html_output.push_str(include_str!("static/prism.min.js"));
-        html_output.push_str(include_str!("static/prism-rust.min.js"));
-        html_output.push_str(include_str!("static/line-numbers.js"));
-        html_output.push_str("</script>");
-    };
+        if let Some((depth, started)) = hiding.as_mut() {
+            // the item ends with the line bringing us back to the top level
+            // that ends in `;` or `}`, or the next empty line.
+            let line_code: String = code.iter()
+                .filter(|t| matches!(t, Token::Code(_) | Token::Literal(_)))
+                .map(Token::text)
+                .collect();
+            let line_code = line_code.trim();
+            if !line_code.is_empty() {
+                *started = true;
+                *depth += lexer::brace_balance(code);
+            }
+            comment_in_code = !lexer.at_code();
+            if *started && *depth <= 0 &&
+                    (line_code.is_empty() || line_code.ends_with(';') || line_code.ends_with('}')) {
+                hiding = None;
+            }
+            continue;
+        }
 
-    options.extra_meta.map(|f| include_static(f, &mut html_output));
+        match directive {
+            Some(Ok(Directive::SkipStart)) => skipping = Some(idx + 1),
+            Some(Ok(Directive::SkipEnd)) => options.warn(name, idx + 1,
+                "`skip-end` without a `skip-start`".to_string()),
+            Some(Ok(Directive::HideNextItem)) => hiding = Some((0, false)),
+            Some(Ok(Directive::Break)) => {
+                let mut next = Block::new(idx + 2, language);
+                if current_block.comment.is_empty() && current_block.code.is_empty() {
+                    next.raw = std::mem::take(&mut current_block.raw);
+                    current_block = next;
+                } else {
+                    blocks.push(std::mem::replace(&mut current_block, next));
+                }
+                previous = None;
+            }
+            Some(Err(unknown)) => options.warn(name, idx + 1,
+                format!("unknown directive `{}`", unknown)),
+            None => {}
+        }
+        if directive.is_some() {
+            continue;
+        }
 
-    html_output.push_str("</head><body>");
+        // depending on the mode, some comments stay with the code
+        let keep_as_prose = match options.prose {
+            Prose::All => true,
+            Prose::Doc => comments.iter().all(|t| match t {
+                Token::LineComment(com_type, _) | Token::BlockComment { kind: com_type, .. } =>
+                    com_type.is_doc(),
+                _ => true,
+            }),
+            Prose::TopLevel => line_depth <= 0,
+        };
+        if !keep_as_prose {
+            code = &tokens[..];
+            comments = &[];
+        }
+        comment_in_code = !lexer.at_code()
+            && matches!(code.last(), Some(Token::BlockComment { closes: false, .. }));
+        let mut prose: Vec<(CommentType, String)> = comments.iter()
+            .filter_map(|t| comment_content(language, t))
+            .map(|(com_type, content)| (com_type, content.to_string()))
+            .collect();
 
-    options.extra_header.map(|f| include_static(f, &mut html_output));
+        // `#[doc = "..."]` and `#![doc = include_str!("...")]` are prose, too,
+        // where comments would be
+        let doc = if keep_as_prose && at_code && comments.is_empty() && *language == language::RUST {
+            attribute::parse_doc(&line_str)
+        } else {
+            None
+        };
+        if let Some(doc) = &doc {
+            let com_type = if doc.inner { CommentType::Bang } else { CommentType::Doc };
+            let text = match &doc.value {
+                DocValue::Text(text) => text.clone(),
+                DocValue::Include(file) => {
+                    let path = Path::new(name).parent().unwrap_or_else(|| Path::new("")).join(file);
+                    let text = fs::read_to_string(&path)
+                        .map_err(|source| Error::MissingInclude { path, source })?;
+                    text.strip_prefix('\u{feff}').map(str::to_string).unwrap_or(text)
+                }
+            };
+            prose.extend(text.lines().map(|l| (com_type, l.to_string())));
+            code = &[];
+        }
 
-    html_output.push_str("<div id=\"container\"><div id=\"main\">");
+        // a line with comments following code may start a new block, even
+        // if there's nothing worth adding to the prose on it, like a `/**`
+        let first_comment = comments.iter()
+            .find_map(|t| match t {
+                Token::LineComment(com_type, _) | Token::BlockComment { kind: com_type, .. } =>
+                    Some(*com_type),
+                _ => None,
+            })
+            .or_else(|| doc.as_ref().map(|d| if d.inner { CommentType::Bang } else { CommentType::Doc }))
+            .map(CommentType::kind);
+        if let (Some(Segment::Code), Some(kind)) = (previous, first_comment) {
+            if segmenter.starts_block(Segment::Code, Segment::Comment(kind)) {
+                blocks.push(current_block);
+                current_block = Block::new(idx + 1, language);
+                previous = None;
+            }
+        }
 
-    for (i, block) in blocks.into_iter().enumerate() {
-        html_output.push_str(&format!(include_str!("static/block_before.html"), index=i));
+        for (com_type, content) in prose {
+            let kind = com_type.kind();
+            if let Some(Segment::Comment(previous_kind)) = previous {
+                if segmenter.starts_block(Segment::Comment(previous_kind), Segment::Comment(kind)) {
+                    blocks.push(current_block);
+                    current_block = Block::new(idx + 1, language);
+                }
+            }
+            previous = Some(Segment::Comment(kind));
+            current_block.kind = Some(com_type);
+            current_block.comment.push(content);
+            current_block.end_line = idx + 1;
+        }
 
-        html::push_html(&mut html_output, Parser::new(&block.comment.join("\n")));
+        if !code.is_empty() || tokens.is_empty() {
+            if let Some(previous) = previous {
+                if segmenter.starts_block(previous, Segment::Code) {
+                    blocks.push(current_block);
+                    current_block = Block::new(idx + 1, language);
+                }
+            }
+            previous = Some(Segment::Code);
 
-        if block.has_code() {
-            html_output.push_str(&format!(include_str!("static/block_code.html"),
-                code=block.code.join("\n").replace("<", "&lt;"), start=block.starting_line));
+            // a comment trailing the code becomes an annotation of this line
+            if let Some((last, before)) = code.split_last() {
+                let trailing = match last {
+                    Token::LineComment(..) => true,
+                    Token::BlockComment { opens, closes, .. } => *opens && *closes,
+                    Token::Code(_) | Token::Literal(_) => false,
+                };
+                if trailing && before.iter().any(is_code) {
+                    if let Some((_, content)) = comment_content(language, last) {
+                        current_block.annotations.push(Annotation {
+                            line: current_block.code.len(),
+                            column: before.iter().map(|t| t.text().len()).sum(),
+                            comment: content.trim().to_string(),
+                        });
+                    }
+                }
+            }
+            current_block.code_lines.push(idx + 1);
+            current_block.code.push(code.iter().map(Token::text).collect());
+            current_block.end_line = idx + 1;
         }
-
-        html_output.push_str(include_str!("static/block_after.html"));
+    }
+    current_block.raw.extend(raw.take());
+    blocks.push(current_block);
+    if let Some(line) = skipping {
+        options.warn(name, line, "`skip-start` without a `skip-end`".to_string());
+    }
+    // skipped and hidden parts may leave blocks behind with nothing in them,
+    // whose lines go to the block that follows, or the last one
+    let mut left_over: Vec<String> = Vec::new();
+    let mut kept: Vec<Block> = Vec::new();
+    for mut block in blocks {
+        if block.comment.is_empty() && !block.has_code() {
+            left_over.append(&mut block.raw);
+            continue;
+        }
+        left_over.append(&mut block.raw);
+        block.raw = std::mem::take(&mut left_over);
+        kept.push(block);
+    }
+    match kept.last_mut() {
+        Some(last) => last.raw.append(&mut left_over),
+        // nothing but skipped lines, which still need a block to keep them
+        None if !left_over.is_empty() => {
+            let mut block = Block::new(1, language);
+            block.raw = left_over;
+            kept.push(block);
+        }
+        None => {}
+    }
+    let mut blocks = kept;
+    let path: Arc<Path> = Arc::from(Path::new(name));
+    for block in blocks.iter_mut() {
+        block.dedent_comment();
+        block.path = path.clone();
     }
 
+    if *language == language::RUST {
+        let module_path = module_path.map(|p| p.to_string())
+            .unwrap_or_else(|| items::module_path_for(name));
+        match items::collect(&source, &module_path) {
+            Ok(items) => {
+                for block in blocks.iter_mut() {
+                    block.item = items.iter()
+                        .filter(|i| block.code_lines.contains(&i.line))
+                        .min_by_key(|i| i.line)
+                        .cloned();
+                }
+            }
+            Err(err) => options.warn(name, err.span().start().line,
+                format!("could not parse items, anchors fall back to section numbers: {}", err)),
+        }
+    }
+    Ok(blocks)
+}
 
-    html_output.push_str("</div></div>");
-
-    options.extra_footer.map(|f| include_static(f, &mut html_output));
+// Build a versioned JSON document from the blocks of the `files`.
+pub fn build_json<I: IntoIterator<Item=SourceFile>>(files: I) -> Result<String> {
+    render(files, &mut JsonRenderer::default())
+}
 
-    html_output.push_str("</body></html>");
-    
-    return html_output;
+// Build a full HTML document from the blocks of the `files`, each introduced
+// by a header. This function also inlines the CSS.
+pub fn build_html<I: IntoIterator<Item=SourceFile>>(files: I, options: Options) -> Result<String> {
+    render(files, &mut HtmlRenderer::new(options)?)
 }
\ No newline at end of file
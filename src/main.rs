@@ -1,11 +1,14 @@
 
-use clap::{Arg, App};
+use clap::{Arg, App, ArgMatches};
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::process;
 
-use dadada::{Block, Options, extract, build_html};
+use dadada::{Config, Package, SourceFile, Error, ExtractOptions, HtmlRenderer, JsonRenderer, Language, Options,
+    Renderer, build_site, extract, extract_crate, extract_package, extract_reader, read_manifest, render};
 
 fn main() {
+    let languages: Vec<&str> = Language::all().iter().map(|l| l.name).collect();
     let matches = App::new("dadada")
         .version("0.9.5-dev")
         .author("Benjamin Kampmann <ben@gnunicorn.org>, Rui Vieira <ruidevieira@googlemail.com>")
@@ -25,12 +28,64 @@ fn main() {
             .long("no-js")
             .required(false)
             .help("Do not add Javascript to output"))
+        .arg(Arg::with_name("no_annotations")
+            .long("no-annotations")
+            .required(false)
+            .help("Leave end-of-line comments inline instead of rendering them as margin notes"))
+        .arg(Arg::with_name("no_toc")
+            .long("no-toc")
+            .required(false)
+            .help("Do not add the menu listing files and the headings of their prose"))
+        .arg(Arg::with_name("no_highlight")
+            .long("no-highlight")
+            .required(false)
+            .help("Leave highlighting Rust code to the Javascript instead of doing it while rendering"))
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .value_name("LANGUAGE")
+            .help("read all input files as this language, detected by file extension if not given")
+            .possible_values(&languages)
+            .takes_value(true))
+        .arg(Arg::with_name("prose")
+            .long("prose")
+            .value_name("MODE")
+            .help("which comments to render as prose: all of them, only doc comments or only those outside of braces")
+            .possible_values(&["all", "doc", "top-level"])
+            .default_value("all")
+            .takes_value(true))
+        .arg(Arg::with_name("lossy")
+            .long("lossy")
+            .help("Decode files that aren't valid UTF-8 anyway, with a warning, instead of failing"))
+        .arg(Arg::with_name("crate")
+            .long("crate")
+            .value_name("FILE")
+            .help("crate root to render with all its modules, in the order they are declared")
+            .takes_value(true))
+        .arg(Arg::with_name("manifest_path")
+            .long("manifest-path")
+            .value_name("FILE")
+            .help("Cargo.toml of a package or workspace to render all targets of, with defaults from \
+                   [package.metadata.dadada]")
+            .takes_value(true))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("what to render: an HTML page, or the blocks as JSON")
+            .possible_values(&["html", "json"])
+            .default_value("html")
+            .takes_value(true))
         .arg(Arg::with_name("output")
             .short("o")
             .long("output")
             .value_name("FILE")
             .help("target file to render to, stdout if not given")
             .takes_value(true))
+        .arg(Arg::with_name("output_dir")
+            .long("output-dir")
+            .value_name("DIR")
+            .help("render a site into DIR instead, with a page for every file and an index of them")
+            .conflicts_with_all(&["output", "format"])
+            .takes_value(true))
 
         .arg(Arg::with_name("extra_meta")
             .long("meta")
@@ -47,38 +102,104 @@ fn main() {
             .value_name("FILE")
             .help("extra html/markdown to include at the end of html body")
             .takes_value(true))
+        .arg(Arg::with_name("template_dir")
+            .long("template-dir")
+            .value_name("DIR")
+            .help("directory with templates to use instead of the built-in ones, see the README")
+            .takes_value(true))
 
         .arg(Arg::with_name("input")
             .value_name("FILE")
-            .help("rust source files")
-            .required(true)
+            .help("rust source files, `-` to read from stdin")
+            .required_unless_one(&["crate", "manifest_path"])
             .multiple(true)
             .takes_value(true))
         .get_matches();
 
-    let output = build_html(
-        matches.values_of("input").expect("This is required")
-            .map(|i| {
-                let mut blocks = extract(i.to_string());
-                let path = Path::new(i);
-                let title = path.file_name().expect("Must be a file").to_str().unwrap_or("");
-                let dir = path.parent().map(|i| i.to_str().unwrap_or("")).unwrap_or("");
-                blocks.insert(0, Block::new_file(title, dir));
-                blocks
-            }).flatten(),
-        Options {
-            title: matches.value_of("title").unwrap_or("").to_string(),
-            with_css: !matches.is_present("no_css"),
-            with_js: !matches.is_present("no_js"),
-            extra_meta: matches.value_of("extra_meta").map(|s| s.to_string()),
-            extra_header: matches.value_of("extra_header").map(|s| s.to_string()),
-            extra_footer: matches.value_of("extra_footer").map(|s| s.to_string()),  
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        if let Error::Encoding { .. } = err {
+            eprintln!("note: pass `--lossy` to decode it anyway");
+        }
+        process::exit(err.exit_code());
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let packages = match matches.value_of("manifest_path") {
+        Some(path) => read_manifest(path)?,
+        None => Vec::new(),
+    };
+    // the first package, the root of a workspace, configures the output
+    let config = packages.first().map(|p| p.config.clone()).unwrap_or_default();
+
+    // flags given on the command line win over the package's settings
+    let options_for = |config: &Config| ExtractOptions {
+        language: matches.value_of("lang").and_then(Language::by_name),
+        on_warning: None,
+        prose: match (matches.occurrences_of("prose"), config.prose) {
+            (0, Some(prose)) => prose,
+            _ => matches.value_of("prose").unwrap_or("all").parse().expect("Checked by clap"),
         },
-    );
+        module_path: None,
+        lossy: matches.is_present("lossy"),
+        segmenter: None,
+    };
+    let extract_options = options_for(&config);
+
+    let mut files = Vec::new();
+    for package in packages.iter() {
+        files.extend(extract_package(package, &options_for(&package.config))?);
+    }
+    if let Some(root) = matches.value_of("crate") {
+        files.extend(extract_crate(root, &extract_options)?);
+    }
+    for i in matches.values_of("input").into_iter().flatten() {
+        if i == "-" {
+            files.push(SourceFile::new("stdin", extract_reader("stdin", io::stdin(), &extract_options)?));
+            continue;
+        }
+        files.push(SourceFile::new(i, extract(i.to_string(), &extract_options)?));
+    }
+
+    if let Some(dir) = matches.value_of("output_dir") {
+        build_site(files, page_options(matches, packages.first()), dir)?;
+        return Ok(());
+    }
+
+    let mut renderer: Box<dyn Renderer> = match matches.value_of("format") {
+        Some("json") => Box::new(JsonRenderer::default()),
+        _ => Box::new(HtmlRenderer::new(page_options(matches, packages.first()))?),
+    };
+    let output = render(files, renderer.as_mut())?;
 
     match matches.value_of("output") {
-        Some(f) => fs::write(f, output).expect("Could not write to output file."),
+        Some(f) => fs::write(f, output)
+            .map_err(|source| Error::Io { path: f.into(), source })?,
         None => println!("{}",  output),
     }
+    Ok(())
 }
 
+// The options of the HTML page, from the command line or else the package
+fn page_options(matches: &ArgMatches, package: Option<&Package>) -> Options {
+    let mut options = package.map(|p| p.options()).unwrap_or_default();
+    if let Some(title) = matches.value_of("title") {
+        options.title = title.to_string();
+    }
+    options.with_css &= !matches.is_present("no_css");
+    options.with_js &= !matches.is_present("no_js");
+    options.with_annotations &= !matches.is_present("no_annotations");
+    options.with_toc &= !matches.is_present("no_toc");
+    options.with_highlighting &= !matches.is_present("no_highlight");
+    for (arg, extra) in [("extra_meta", &mut options.extra_meta),
+        ("extra_header", &mut options.extra_header),
+        ("extra_footer", &mut options.extra_footer),
+        ("template_dir", &mut options.template_dir)]
+    {
+        if let Some(f) = matches.value_of(arg) {
+            *extra = Some(f.to_string());
+        }
+    }
+    options
+}
@@ -0,0 +1,433 @@
+// Rather than being handed files one by one, we may be pointed at a
+// `Cargo.toml`. We find the targets of its package the way cargo does:
+// those listed explicitly, plus `src/lib.rs`, `src/main.rs`, `src/bin/*`,
+// `examples/*`, `tests/*` and `benches/*` unless `autobins` and friends
+// turn that off. A workspace gives us all of its members.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::{Options, Prose};
+
+/// A package found through its manifest
+#[derive(Clone, Debug)]
+pub struct Package {
+    /// `name` of the package
+    pub name: String,
+    /// `description` of the package, if any
+    pub description: Option<String>,
+    /// `authors` of the package
+    pub authors: Vec<String>,
+    /// The directory the manifest is in, which all paths are relative to
+    pub manifest_dir: PathBuf,
+    /// The targets of the package, the library first
+    pub targets: Vec<Target>,
+    /// Settings from `[package.metadata.dadada]`
+    pub config: Config,
+}
+
+/// A library, binary, example, test or benchmark of a package
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    /// What kind of target this is
+    pub kind: TargetKind,
+    /// Name of the target, e.g. the binary's name
+    pub name: String,
+    /// The root file of the target
+    pub path: PathBuf,
+}
+
+/// Kinds of targets a package may have
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Example,
+    Test,
+    Bench,
+}
+
+/// Settings read from `[package.metadata.dadada]`, all optional
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// HTML title, the package name if not given
+    pub title: Option<String>,
+    /// Whether to include the static css
+    pub css: Option<bool>,
+    /// Whether to include the static javascript
+    pub js: Option<bool>,
+    /// Whether to render end-of-line comments as margin annotations
+    pub annotations: Option<bool>,
+    /// Whether to add a menu listing the files and the headings of their prose
+    pub toc: Option<bool>,
+    /// Whether to highlight Rust code while rendering
+    pub highlight: Option<bool>,
+    /// Which comments become prose
+    pub prose: Option<Prose>,
+    /// File with extra for meta, relative to the manifest
+    pub meta: Option<PathBuf>,
+    /// File with extra for header, relative to the manifest
+    pub header: Option<PathBuf>,
+    /// File with extra for footer, relative to the manifest
+    pub footer: Option<PathBuf>,
+    /// Directory with templates overriding the built-in ones, relative to the manifest
+    pub template_dir: Option<PathBuf>,
+    /// Kinds of targets to render, all of them if not given
+    pub targets: Option<Vec<TargetKind>>,
+}
+
+impl TargetKind {
+    /// The name of the kind, as used in `Cargo.toml`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+        }
+    }
+}
+
+impl Package {
+    /// The targets to render, as selected by the `targets` setting
+    pub fn selected_targets(&self) -> impl Iterator<Item=&Target> {
+        self.targets.iter().filter(move |t| match &self.config.targets {
+            Some(kinds) => kinds.contains(&t.kind),
+            None => true,
+        })
+    }
+
+    /// Rendering options as configured for the package, titled after the
+    /// package unless configured otherwise
+    pub fn options(&self) -> Options {
+        let path = |p: &Option<PathBuf>| p.as_ref().map(|p| p.to_string_lossy().into_owned());
+        Options {
+            title: self.config.title.clone().unwrap_or_else(|| self.name.clone()),
+            with_css: self.config.css.unwrap_or(true),
+            with_js: self.config.js.unwrap_or(true),
+            extra_meta: path(&self.config.meta),
+            extra_header: path(&self.config.header),
+            extra_footer: path(&self.config.footer),
+            with_annotations: self.config.annotations.unwrap_or(true),
+            template_dir: path(&self.config.template_dir),
+            with_toc: self.config.toc.unwrap_or(true),
+            with_highlighting: self.config.highlight.unwrap_or(true),
+        }
+    }
+}
+
+// The parts of `Cargo.toml` we care about. Anything else is ignored.
+#[derive(Deserialize)]
+struct Manifest {
+    package: Option<PackageDef>,
+    workspace: Option<WorkspaceDef>,
+    lib: Option<TargetDef>,
+    #[serde(default)]
+    bin: Vec<TargetDef>,
+    #[serde(default)]
+    example: Vec<TargetDef>,
+    #[serde(default)]
+    test: Vec<TargetDef>,
+    #[serde(default)]
+    bench: Vec<TargetDef>,
+}
+
+#[derive(Deserialize)]
+struct PackageDef {
+    name: String,
+    description: Option<Inheritable<String>>,
+    authors: Option<Inheritable<Vec<String>>>,
+    autobins: Option<bool>,
+    autoexamples: Option<bool>,
+    autotests: Option<bool>,
+    autobenches: Option<bool>,
+    metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    dadada: Option<Config>,
+}
+
+// `description.workspace = true` takes the value from `[workspace.package]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Inheritable<T> {
+    Value(T),
+    Workspace { workspace: bool },
+}
+
+#[derive(Deserialize)]
+struct WorkspaceDef {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default, rename = "default-members")]
+    default_members: Vec<String>,
+    package: Option<WorkspacePackage>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+struct WorkspacePackage {
+    description: Option<String>,
+    authors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct TargetDef {
+    name: Option<String>,
+    path: Option<PathBuf>,
+}
+
+impl<T> Inheritable<T> {
+    fn resolve(self, inherited: Option<T>) -> Option<T> {
+        match self {
+            Inheritable::Value(value) => Some(value),
+            Inheritable::Workspace { workspace: true } => inherited,
+            Inheritable::Workspace { workspace: false } => None,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Manifest> {
+    let source = fs::read_to_string(path)
+        .map_err(|source| Error::Io { path: path.into(), source })?;
+    toml::from_str(&source)
+        .map_err(|err| Error::Manifest { path: path.into(), message: err.message().to_string() })
+}
+
+// Whether `name` matches `pattern`, which may contain `*` wildcards
+fn matches_glob(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => name.strip_prefix(prefix).is_some_and(|name| {
+            (0..=name.len()).filter(|i| name.is_char_boundary(*i))
+                .any(|i| matches_glob(rest, &name[i..]))
+        }),
+    }
+}
+
+// The directories matching the workspace member `pattern`, which may use `*`
+// within any of its components.
+fn expand_member(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut found = vec![dir.to_path_buf()];
+    for component in Path::new(pattern).iter().filter_map(|c| c.to_str()) {
+        if !component.contains('*') {
+            found = found.into_iter().map(|d| d.join(component)).collect();
+            continue;
+        }
+        found = found.into_iter()
+            .flat_map(|d| {
+                let mut entries: Vec<PathBuf> = fs::read_dir(&d).into_iter().flatten()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_name().to_str().is_some_and(|n| matches_glob(component, n)))
+                    .map(|e| e.path())
+                    .collect();
+                entries.sort();
+                entries
+            })
+            .collect();
+    }
+    found.into_iter().filter(|d| d.is_dir()).collect()
+}
+
+// Targets of one kind: the explicitly listed ones first, then those found in
+// `dir`, as `dir/name.rs` or `dir/name/main.rs`.
+fn targets(root: &Path, kind: TargetKind, dir: &str, listed: Vec<TargetDef>, auto: bool) -> Vec<Target> {
+    let mut targets = Vec::new();
+    for def in listed {
+        let name = def.name.unwrap_or_default();
+        let path = match def.path {
+            Some(path) => root.join(path),
+            None => {
+                let file = root.join(dir).join(format!("{}.rs", name));
+                if file.is_file() { file } else { root.join(dir).join(&name).join("main.rs") }
+            }
+        };
+        targets.push(Target { kind, name, path });
+    }
+    if !auto {
+        return targets;
+    }
+    let entries = match fs::read_dir(root.join(dir)) {
+        Ok(entries) => entries,
+        Err(_) => return targets,
+    };
+    let mut found: Vec<Target> = entries.filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            if path.is_dir() {
+                let main = path.join("main.rs");
+                let name = e.file_name().to_str()?.to_string();
+                return if main.is_file() { Some(Target { kind, name, path: main }) } else { None };
+            }
+            if path.extension()? != "rs" {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(Target { kind, name, path })
+        })
+        .filter(|t| !targets.iter().any(|l| l.name == t.name || l.path == t.path))
+        .collect();
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    targets.extend(found);
+    targets
+}
+
+fn package(manifest_path: &Path, manifest: Manifest, def: PackageDef, inherited: WorkspacePackage)
+    -> Package
+{
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    let mut all = Vec::new();
+
+    let lib = match manifest.lib {
+        Some(lib) => Some(root.join(lib.path.unwrap_or_else(|| "src/lib.rs".into()))),
+        None => Some(root.join("src/lib.rs")).filter(|p| p.is_file()),
+    };
+    if let Some(path) = lib {
+        all.push(Target { kind: TargetKind::Lib, name: def.name.replace('-', "_"), path });
+    }
+
+    let auto_bins = def.autobins.unwrap_or(true);
+    let main = root.join("src/main.rs");
+    if auto_bins && main.is_file() && !manifest.bin.iter().any(|b| b.name.as_ref() == Some(&def.name)) {
+        all.push(Target { kind: TargetKind::Bin, name: def.name.clone(), path: main });
+    }
+    let mut bins = manifest.bin;
+    for bin in bins.iter_mut() {
+        // the binary named after the package defaults to `src/main.rs`
+        if bin.path.is_none() && bin.name.as_ref() == Some(&def.name) {
+            bin.path = Some("src/main.rs".into());
+        }
+    }
+    for bin in targets(&root, TargetKind::Bin, "src/bin", bins, auto_bins) {
+        if !all.iter().any(|t| t.path == bin.path) {
+            all.push(bin);
+        }
+    }
+    all.extend(targets(&root, TargetKind::Example, "examples", manifest.example,
+        def.autoexamples.unwrap_or(true)));
+    all.extend(targets(&root, TargetKind::Test, "tests", manifest.test,
+        def.autotests.unwrap_or(true)));
+    all.extend(targets(&root, TargetKind::Bench, "benches", manifest.bench,
+        def.autobenches.unwrap_or(true)));
+
+    let mut config = def.metadata.and_then(|m| m.dadada).unwrap_or_default();
+    for path in config.meta.iter_mut().chain(config.header.iter_mut()).chain(config.footer.iter_mut())
+        .chain(config.template_dir.iter_mut())
+    {
+        *path = root.join(&*path);
+    }
+
+    let WorkspacePackage { description, authors } = inherited;
+    Package {
+        name: def.name,
+        description: def.description.and_then(|d| d.resolve(description)),
+        authors: def.authors.and_then(|a| a.resolve(authors)).unwrap_or_default(),
+        manifest_dir: root,
+        targets: all,
+        config,
+    }
+}
+
+/// The packages of the manifest at `manifest_path`: its package, followed by
+/// all members if it is the root of a workspace.
+pub fn read_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<Vec<Package>> {
+    let manifest_path = manifest_path.as_ref();
+    let mut manifest = read(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    let workspace = manifest.workspace.take();
+    let inherited = workspace.as_ref().and_then(|w| w.package.clone()).unwrap_or_default();
+
+    let mut packages = Vec::new();
+    if let Some(def) = manifest.package.take() {
+        packages.push(package(manifest_path, manifest, def, inherited.clone()));
+    }
+    if let Some(workspace) = workspace {
+        let excluded: Vec<PathBuf> = workspace.exclude.iter().map(|e| root.join(e)).collect();
+        for member in workspace.members.iter().flat_map(|m| expand_member(&root, m)) {
+            let member_manifest = member.join("Cargo.toml");
+            if member == root || excluded.contains(&member) || !member_manifest.is_file() {
+                continue;
+            }
+            let mut manifest = read(&member_manifest)?;
+            if let Some(def) = manifest.package.take() {
+                packages.push(package(&member_manifest, manifest, def, inherited.clone()));
+            }
+        }
+    }
+    if packages.is_empty() {
+        return Err(Error::Manifest {
+            path: manifest_path.into(),
+            message: "neither a package nor a workspace with members".to_string(),
+        });
+    }
+    Ok(packages)
+}
+
+/// The packages cargo acts on with the manifest at `manifest_path` when none
+/// are selected: the `default-members` of a workspace root, its own package,
+/// or all members of a virtual workspace
+pub fn default_packages<P: AsRef<Path>>(manifest_path: P) -> Result<Vec<Package>> {
+    let manifest_path = manifest_path.as_ref();
+    let manifest = read(manifest_path)?;
+    let packages = read_manifest(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    match manifest.workspace {
+        Some(workspace) if !workspace.default_members.is_empty() => {
+            let defaults: Vec<PathBuf> = workspace.default_members.iter()
+                .flat_map(|m| expand_member(root, m))
+                .collect();
+            Ok(packages.into_iter().filter(|p| defaults.contains(&p.manifest_dir)).collect())
+        }
+        Some(_) if manifest.package.is_none() => Ok(packages),
+        _ => Ok(packages.into_iter().take(1).collect()),
+    }
+}
+
+/// The manifest cargo picks when run in `dir`: the nearest `Cargo.toml` in
+/// `dir` or any of its parents
+pub fn find_manifest<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+    dir.as_ref().ancestors()
+        .map(|d| d.join("Cargo.toml"))
+        .find(|m| m.is_file())
+}
+
+/// The root manifest of the workspace the package at `manifest_path` is a
+/// member of, or `manifest_path` itself if it isn't part of any workspace
+pub fn workspace_root<P: AsRef<Path>>(manifest_path: P) -> Result<PathBuf> {
+    let manifest_path = manifest_path.as_ref();
+    let canonical = |p: &Path| fs::canonicalize(p).map_err(|source| Error::Io { path: p.into(), source });
+    let package_dir = canonical(manifest_path)?.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for dir in package_dir.ancestors() {
+        let candidate = dir.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+        let workspace = match read(&candidate)?.workspace {
+            Some(workspace) => workspace,
+            None => continue,
+        };
+        if dir == package_dir {
+            return Ok(candidate);
+        }
+        let is_member = workspace.members.iter()
+            .flat_map(|m| expand_member(dir, m))
+            .filter_map(|m| fs::canonicalize(m).ok())
+            .any(|m| m == package_dir);
+        let is_excluded = workspace.exclude.iter()
+            .filter_map(|e| fs::canonicalize(dir.join(e)).ok())
+            .any(|e| package_dir.starts_with(e));
+        if is_member && !is_excluded {
+            return Ok(candidate);
+        }
+    }
+    Ok(manifest_path.to_path_buf())
+}
@@ -0,0 +1,134 @@
+// Starting from a crate root, we follow `mod foo;` declarations the way
+// rustc does: `foo.rs` or `foo/mod.rs` next to a `lib.rs`, `main.rs` or
+// `mod.rs`, `bar/foo.rs` for a declaration in `bar.rs`, inline `mod x { }`
+// adding a directory and `#[path = "..."]` overriding all of that.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::ExtractOptions;
+
+// A file of the crate, in depth-first module order
+pub(crate) struct CrateFile {
+    pub(crate) path: PathBuf,
+    pub(crate) module_path: String,
+    // the (1-based) lines of `mod foo;` declarations and the file each of
+    // them was resolved to
+    pub(crate) declarations: Vec<(usize, PathBuf)>,
+}
+
+struct Walker<'a> {
+    options: &'a ExtractOptions,
+    seen: HashSet<PathBuf>,
+    files: Vec<CrateFile>,
+}
+
+// Where the children of a module are looked for
+struct ModuleDir {
+    // the directory `mod foo;` resolves `foo.rs` and `foo/mod.rs` in
+    dir: PathBuf,
+    // the directory `#[path]` is relative to
+    path_base: PathBuf,
+}
+
+// `#[path = "..."]` of a module, if given
+fn path_attribute(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter()
+        .filter(|a| a.path().is_ident("path"))
+        .find_map(|a| match &a.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }), ..
+            }) => Some(s.value()),
+            _ => None,
+        })
+}
+
+impl<'a> Walker<'a> {
+    // Add the file at `path` and, right after it, all the files of its
+    // submodules. Files declaring their modules through `#[path]` and crate
+    // roots own the directory they are in, others the one named after them.
+    fn file(&mut self, path: &Path, module_path: String, owns_dir: bool) -> Result<()> {
+        if !self.seen.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+        // whether the file may be read lossily is up to extracting it, here
+        // we only look for its modules
+        let source = fs::read(path)
+            .map_err(|source| Error::Io { path: path.into(), source })?;
+        let source = String::from_utf8_lossy(&source);
+        let index = self.files.len();
+        self.files.push(CrateFile {
+            path: path.into(),
+            module_path: module_path.clone(),
+            declarations: Vec::new(),
+        });
+
+        let file = match syn::parse_file(&source) {
+            Ok(file) => file,
+            Err(err) => {
+                self.options.warn(&path.to_string_lossy(), err.span().start().line,
+                    format!("could not parse file, its modules are not followed: {}", err));
+                return Ok(());
+            }
+        };
+        let parent = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let dir = if owns_dir {
+            parent.clone()
+        } else {
+            parent.join(path.file_stem().unwrap_or_default())
+        };
+        self.items(&file.items, path, index, &module_path, ModuleDir { dir, path_base: parent })
+    }
+
+    fn items(&mut self, items: &[syn::Item], file: &Path, index: usize, module_path: &str,
+        at: ModuleDir) -> Result<()>
+    {
+        for item in items {
+            let module = match item {
+                syn::Item::Mod(m) => m,
+                _ => continue,
+            };
+            let name = module.ident.to_string();
+            let child_path = format!("{}::{}", module_path, name);
+            let path_attribute = path_attribute(&module.attrs);
+
+            if let Some((_, content)) = &module.content {
+                let dir = match &path_attribute {
+                    Some(p) => at.path_base.join(p),
+                    None => at.dir.join(&name),
+                };
+                self.items(content, file, index, &child_path,
+                    ModuleDir { dir: dir.clone(), path_base: dir })?;
+                continue;
+            }
+
+            let line = module.mod_token.span.start().line;
+            let (candidates, owns_dir) = match &path_attribute {
+                Some(p) => (vec![at.path_base.join(p)], true),
+                None => (vec![at.dir.join(format!("{}.rs", name)), at.dir.join(&name).join("mod.rs")], false),
+            };
+            match candidates.iter().find(|c| c.is_file()) {
+                Some(found) => {
+                    // `foo/mod.rs` owns `foo/` just like a crate root does
+                    let owns_dir = owns_dir || found.file_name() == Some("mod.rs".as_ref());
+                    self.files[index].declarations.push((line, found.clone()));
+                    self.file(found, child_path, owns_dir)?;
+                }
+                None => self.options.warn(&file.to_string_lossy(), line,
+                    format!("no file found for module `{}`, looked for `{}`", name,
+                        candidates.iter().map(|c| c.display().to_string())
+                            .collect::<Vec<_>>().join("` and `"))),
+            }
+        }
+        Ok(())
+    }
+}
+
+// All files of the crate with the root file `root`, in depth-first module
+// order.
+pub(crate) fn walk(root: &Path, options: &ExtractOptions) -> Result<Vec<CrateFile>> {
+    let mut walker = Walker { options, seen: HashSet::new(), files: Vec::new() };
+    walker.file(root, "crate".to_string(), true)?;
+    Ok(walker.files)
+}
@@ -0,0 +1,66 @@
+// Turning extracted files into output is up to a `Renderer`, which `render`
+// walks through the files and their blocks. HTML and JSON are built in.
+use crate::error::Result;
+use crate::{Block, SourceFile};
+
+/// An output format
+///
+/// [`render`](fn.render.html) calls these in document order: the start of
+/// the document, then for every file its start, for every block of the file
+/// `block`, `code` if the block has any and `block_end`, then the end of the
+/// file, and last the end of the document, which returns the output. All but
+/// `document_end` do nothing unless implemented.
+pub trait Renderer {
+    /// Before anything else
+    fn document_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Before the blocks of `file`
+    fn file_start(&mut self, _file: &SourceFile) -> Result<()> {
+        Ok(())
+    }
+
+    /// A block, and with it its prose
+    fn block(&mut self, _block: &Block) -> Result<()> {
+        Ok(())
+    }
+
+    /// The code of the block passed to `block` last, for blocks with code only
+    fn code(&mut self, _block: &Block) -> Result<()> {
+        Ok(())
+    }
+
+    /// After a block and its code
+    fn block_end(&mut self, _block: &Block) -> Result<()> {
+        Ok(())
+    }
+
+    /// After the blocks of `file`
+    fn file_end(&mut self, _file: &SourceFile) -> Result<()> {
+        Ok(())
+    }
+
+    /// After everything else, returning the rendered document
+    fn document_end(&mut self) -> Result<String>;
+}
+
+// Render the `files` with the `renderer`, see `Renderer` for the order of
+// calls.
+pub fn render<I, R>(files: I, renderer: &mut R) -> Result<String>
+    where I: IntoIterator<Item=SourceFile>, R: Renderer + ?Sized
+{
+    renderer.document_start()?;
+    for file in files {
+        renderer.file_start(&file)?;
+        for block in file.blocks.iter() {
+            renderer.block(block)?;
+            if block.has_code() {
+                renderer.code(block)?;
+            }
+            renderer.block_end(block)?;
+        }
+        renderer.file_end(&file)?;
+    }
+    renderer.document_end()
+}
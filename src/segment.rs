@@ -0,0 +1,40 @@
+// Where one block ends and the next begins is up to a `Segmenter`, so users
+// can bring their own rules without touching the extraction loop.
+use crate::CommentKind;
+
+/// What goes into a block, as far as segmenting is concerned
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    /// A comment rendered as prose, of the given kind
+    Comment(CommentKind),
+    /// A line of code, or an empty line
+    Code,
+}
+
+/// Decides where blocks are split
+///
+/// While extracting, every comment and every line of code that could go into
+/// the current block is passed as `next`, together with whatever went into
+/// the block before it as `previous`. Returning `true` starts a new block
+/// with `next`. Empty blocks are never asked about, and a `// dadada: break`
+/// directive always ends a block.
+pub trait Segmenter {
+    /// Whether `next` starts a new block rather than continuing the current
+    /// one, which ends with `previous`
+    fn starts_block(&self, previous: Segment, next: Segment) -> bool;
+}
+
+/// The rules `dadada` splits by, unless told otherwise: a comment following
+/// code starts a new block, and so does a different kind of comment
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultSegmenter;
+
+impl Segmenter for DefaultSegmenter {
+    fn starts_block(&self, previous: Segment, next: Segment) -> bool {
+        match (previous, next) {
+            (Segment::Code, Segment::Comment(_)) => true,
+            (Segment::Comment(previous), Segment::Comment(next)) => previous != next,
+            (_, Segment::Code) => false,
+        }
+    }
+}
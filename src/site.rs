@@ -0,0 +1,118 @@
+// A site of one page per file, in a tree mirroring the files' paths below
+// the directory they all share, with an index of them all. Styles and
+// scripts are written once, next to the index.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::html::{self, HtmlRenderer, SitePage};
+use crate::render::render;
+use crate::template::{IndexEntry, Link, Nav};
+use crate::{CommentKind, Options, SourceFile};
+
+// The pages of the `files`, relative to the root of the site
+fn page_paths(files: &[SourceFile]) -> Vec<String> {
+    // resolving `..` and symlinks, so two paths to a file agree on its page
+    let paths: Vec<PathBuf> = files.iter()
+        .map(|f| fs::canonicalize(&f.path).unwrap_or_else(|_| f.path.clone()))
+        .collect();
+    let dirs: Vec<Vec<Component>> = paths.iter()
+        .map(|p| p.parent().map(|d| d.components().collect()).unwrap_or_default())
+        .collect();
+    let shared = match dirs.first() {
+        Some(first) => (0..first.len())
+            .take_while(|&i| dirs.iter().all(|d| d.get(i) == Some(&first[i])))
+            .count(),
+        None => 0,
+    };
+    paths.iter()
+        .map(|path| {
+            let parts: Vec<_> = path.components().skip(shared)
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part.to_string_lossy()),
+                    _ => None,
+                })
+                .collect();
+            format!("{}.html", parts.join("/"))
+        })
+        .collect()
+}
+
+// The first paragraph of the `//!` docs of the `file`, as HTML
+fn summary(file: &SourceFile) -> Option<String> {
+    let block = file.blocks.iter()
+        .find(|b| matches!(b.comment_kind(), Some(CommentKind::InnerDoc) | Some(CommentKind::InnerBlockDoc)))?;
+    let paragraph: Vec<&str> = block.comment().iter()
+        .map(|l| l.as_str())
+        .skip_while(|l| l.trim().is_empty() || l.starts_with('#'))
+        .take_while(|l| !l.trim().is_empty())
+        .collect();
+    if paragraph.is_empty() {
+        None
+    } else {
+        Some(html::markdown(&paragraph.join("\n")))
+    }
+}
+
+fn write(path: PathBuf, contents: &str) -> Result<PathBuf> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|source| Error::Io { path: dir.into(), source })?;
+    }
+    fs::write(&path, contents).map_err(|source| Error::Io { path: path.clone(), source })?;
+    Ok(path)
+}
+
+/// Render every file on a page of its own into `out_dir`, mirroring their
+/// paths, linked to the previous and next one and listed on an
+/// `index.html`. Styles and scripts are written once, to `dadada.css` and
+/// `dadada.js`, rather than inlined. Returns the files written.
+pub fn build_site<I, P>(files: I, options: Options, out_dir: P) -> Result<Vec<PathBuf>>
+    where I: IntoIterator<Item=SourceFile>, P: AsRef<Path>
+{
+    let out_dir = out_dir.as_ref();
+    let files: Vec<SourceFile> = files.into_iter().collect();
+    let pages = page_paths(&files);
+    let page_of: HashMap<PathBuf, String> = files.iter()
+        .map(|f| f.path.clone())
+        .zip(pages.iter().cloned())
+        .collect();
+
+    let mut written = Vec::new();
+    if options.with_css {
+        written.push(write(out_dir.join(html::CSS_FILE), html::CSS)?);
+    }
+    if options.with_js {
+        written.push(write(out_dir.join(html::JS_FILE), &html::js())?);
+    }
+
+    let mut index = Vec::new();
+    let titles: Vec<String> = files.iter().map(|f| f.path.to_string_lossy().into_owned()).collect();
+    for (i, file) in files.into_iter().enumerate() {
+        let page = &pages[i];
+        let root = "../".repeat(page.matches('/').count());
+        let link = |j: usize| Link { href: format!("{}{}", root, pages[j]), title: titles[j].clone() };
+        let nav = Nav {
+            index: format!("{}index.html", root),
+            prev: i.checked_sub(1).map(link),
+            next: Some(i + 1).filter(|&j| j < pages.len()).map(link),
+        };
+        index.push(IndexEntry { href: page.clone(), path: titles[i].clone(), summary: summary(&file) });
+
+        let title = if options.title.is_empty() {
+            titles[i].clone()
+        } else {
+            format!("{} - {}", titles[i], options.title)
+        };
+        let mut renderer = HtmlRenderer::new(Options { title, ..options.clone() })?;
+        renderer.site = Some(SitePage { root, pages: page_of.clone(), nav: Some(nav) });
+        let output = render(vec![file], &mut renderer)?;
+        written.push(write(out_dir.join(page), &output)?);
+    }
+
+    let title = if options.title.is_empty() { "Index".to_string() } else { options.title.clone() };
+    let mut renderer = HtmlRenderer::new(Options { title, ..options })?;
+    renderer.site = Some(SitePage { root: String::new(), pages: page_of, nav: None });
+    written.push(write(out_dir.join("index.html"), &renderer.index(index)?)?);
+    Ok(written)
+}
@@ -0,0 +1,14 @@
+// Code highlighted while rendering, with its line numbers, is left as it is.
+(function () {
+
+	if (typeof self === 'undefined' || !self.Prism) {
+		return;
+	}
+
+	Prism.hooks.add('before-sanity-check', function (env) {
+		if (env.element.hasAttribute('data-highlighted')) {
+			// without code, Prism and its plugins leave the element alone
+			env.code = '';
+		}
+	});
+}());
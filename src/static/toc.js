@@ -0,0 +1,41 @@
+// Marks the entry of the table of contents for the section scrolled to as
+// the current one.
+(function () {
+
+	if (typeof self === 'undefined' || !self.document || !document.querySelectorAll) {
+		return;
+	}
+
+	var init = function () {
+		var links = Array.prototype.slice.call(document.querySelectorAll('#jump_page a[href^="#"]'));
+		var targets = links.map(function (link) {
+			return document.getElementById(decodeURIComponent(link.getAttribute('href').slice(1)));
+		});
+
+		var highlight = function () {
+			// the last section starting above the top of the window
+			var current = -1;
+			for (var i = 0; i < targets.length; i++) {
+				if (targets[i] && targets[i].getBoundingClientRect().top <= 10) {
+					current = i;
+				}
+			}
+			links.forEach(function (link, i) {
+				if (i === current) {
+					link.classList.add('current');
+				} else {
+					link.classList.remove('current');
+				}
+			});
+		};
+
+		window.addEventListener('scroll', highlight);
+		highlight();
+	};
+
+	if (document.readyState === 'loading') {
+		document.addEventListener('DOMContentLoaded', init);
+	} else {
+		init();
+	}
+}());
@@ -0,0 +1,156 @@
+// The page is put together from templates: the built-in ones in `static/`,
+// unless a template directory has one of the same name. Everything we hand
+// to templates is HTML already, so nothing gets escaped.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use minijinja::{AutoEscape, Environment, ErrorKind};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+// The built-in templates, by name
+const BUILT_IN: &[(&str, &str)] = &[
+    ("head.html", include_str!("static/head.html")),
+    ("foot.html", include_str!("static/foot.html")),
+    ("block_before.html", include_str!("static/block_before.html")),
+    ("block_code.html", include_str!("static/block_code.html")),
+    ("block_after.html", include_str!("static/block_after.html")),
+    ("item_heading.html", include_str!("static/item_heading.html")),
+    ("annotation.html", include_str!("static/annotation.html")),
+    ("module_link.html", include_str!("static/module_link.html")),
+    ("nav.html", include_str!("static/nav.html")),
+    ("index.html", include_str!("static/index.html")),
+    ("toc.html", include_str!("static/toc.html")),
+];
+
+pub(crate) struct Templates {
+    env: Environment<'static>,
+}
+
+// What `head.html` and `foot.html` are rendered with
+#[derive(Serialize)]
+pub(crate) struct Page {
+    pub(crate) title: String,
+    pub(crate) css: Option<&'static str>,
+    pub(crate) js: Option<String>,
+    // the shared styles and scripts of a site, instead of `css` and `js`
+    pub(crate) stylesheet: Option<String>,
+    pub(crate) script: Option<String>,
+    pub(crate) meta: String,
+    pub(crate) header: String,
+    pub(crate) footer: String,
+    pub(crate) nav: Option<Nav>,
+    // filled in for `foot.html` only, once all headings are known
+    pub(crate) toc: Vec<TocEntry>,
+}
+
+// The links between the pages of a site
+#[derive(Clone, Serialize)]
+pub(crate) struct Nav {
+    pub(crate) index: String,
+    pub(crate) prev: Option<Link>,
+    pub(crate) next: Option<Link>,
+}
+
+#[derive(Clone, Serialize)]
+pub(crate) struct Link {
+    pub(crate) href: String,
+    pub(crate) title: String,
+}
+
+// A file, or a heading in the prose of one, in the table of contents
+#[derive(Serialize)]
+pub(crate) struct TocEntry {
+    pub(crate) href: String,
+    // the path of a file, or the text of a heading, as HTML
+    pub(crate) title: String,
+    // 0 for files, else that of the heading
+    pub(crate) level: i32,
+}
+
+// A file on the `index.html` of a site
+#[derive(Serialize)]
+pub(crate) struct IndexEntry {
+    pub(crate) href: String,
+    pub(crate) path: String,
+    // the first paragraph of its `//!` docs, as HTML
+    pub(crate) summary: Option<String>,
+}
+
+// What the `block_*.html` templates are rendered with
+#[derive(Serialize)]
+pub(crate) struct BlockContext {
+    pub(crate) title: String,
+    // `intro` of a package or target, `file` header or `block` of a file
+    pub(crate) kind: &'static str,
+    pub(crate) file: String,
+    pub(crate) index: usize,
+    pub(crate) anchor: String,
+    pub(crate) heading: String,
+    pub(crate) comment: String,
+    pub(crate) start_line: Option<usize>,
+    pub(crate) end_line: Option<usize>,
+    pub(crate) code: String,
+    pub(crate) lines: usize,
+    // the line each line of `code` is found on
+    pub(crate) line_numbers: Vec<usize>,
+    // whether the code is highlighted already, rather than left to Prism
+    pub(crate) highlighted: bool,
+    pub(crate) language: &'static str,
+    pub(crate) annotations: Vec<AnnotationContext>,
+    pub(crate) module_links: Vec<ModuleLinkContext>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct AnnotationContext {
+    pub(crate) line: usize,
+    // offset from the top of the code in `em`
+    pub(crate) top: String,
+    pub(crate) note: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ModuleLinkContext {
+    pub(crate) top: String,
+    pub(crate) anchor: String,
+    // the anchor, on the page of the module if that's another one
+    pub(crate) href: String,
+}
+
+impl Templates {
+    // The built-in templates, overridden by those in `dir`
+    pub(crate) fn new(dir: Option<&Path>) -> Result<Templates> {
+        if let Some(dir) = dir {
+            if !dir.is_dir() {
+                return Err(Error::Io {
+                    path: dir.into(),
+                    source: io::Error::new(io::ErrorKind::NotFound, "template directory not found"),
+                });
+            }
+        }
+        let dir: Option<PathBuf> = dir.map(Path::to_path_buf);
+        let mut env = Environment::new();
+        env.set_keep_trailing_newline(true);
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+        env.set_loader(move |name| {
+            if let Some(dir) = &dir {
+                match fs::read_to_string(dir.join(name)) {
+                    Ok(source) => return Ok(Some(source)),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(minijinja::Error::new(ErrorKind::InvalidOperation,
+                        format!("could not read `{}`", dir.join(name).display())).with_source(err)),
+                }
+            }
+            Ok(BUILT_IN.iter().find(|(n, _)| *n == name).map(|(_, source)| source.to_string()))
+        });
+        Ok(Templates { env })
+    }
+
+    pub(crate) fn render<S: Serialize>(&self, name: &str, context: S) -> Result<String> {
+        self.env.get_template(name)
+            .and_then(|template| template.render(context))
+            .map_err(|err| Error::Template { name: name.to_string(), message: err.to_string() })
+    }
+}
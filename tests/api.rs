@@ -0,0 +1,374 @@
+use dadada::{Block, CommentKind, ExtractOptions, ItemKind, Options, Prose, Renderer, Segment, Segmenter, SourceFile,
+    TargetKind, Visibility, extract_str, extract_reader, build_html, default_packages, read_manifest, render};
+
+fn options() -> Options {
+    Options {
+        title: "In Memory".to_string(),
+        with_css: false,
+        with_js: false,
+        extra_meta: None,
+        extra_header: None,
+        extra_footer: None,
+        with_annotations: true,
+        template_dir: None,
+        with_toc: true,
+        with_highlighting: false,
+    }
+}
+
+#[test]
+fn extract_from_str() -> Result<(), Box<dyn std::error::Error>> {
+    let blocks = extract_str("generated.rs", "/// Made by a macro\npub struct Generated;\n",
+        &ExtractOptions::default())?;
+    let html = build_html(vec![SourceFile::new("generated.rs", blocks)], options())?;
+    assert!(html.contains("<p>Made by a macro</p>"));
+    assert!(html.contains("pub struct Generated;"));
+
+    // names that aren't paths end up in item paths, too
+    let blocks = extract_str("<generated>", "pub struct Generated;\n", &ExtractOptions::default())?;
+    let html = build_html(vec![SourceFile::new("<generated>", blocks)], options())?;
+    assert!(html.contains("<code>crate::&lt;generated>::Generated</code>"));
+    Ok(())
+}
+
+#[test]
+fn blocks_describe_their_source() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "//! The crate\n\n/// A function\n/// documented twice\npub fn f() {\n}\n";
+    let blocks = extract_str("src/lib.rs", source, &ExtractOptions::default())?;
+    let spans: Vec<_> = blocks.iter()
+        .map(|b| (b.comment_kind(), b.start_line(), b.code_line(), b.end_line()))
+        .collect();
+    assert_eq!(spans, vec![
+        (Some(CommentKind::InnerDoc), 1, 2, 2),
+        (Some(CommentKind::OuterDoc), 3, 5, 6),
+    ]);
+    assert_eq!(blocks[1].comment(), ["A function", "documented twice"]);
+    assert_eq!(blocks[1].code(), ["pub fn f() {", "}"]);
+    assert!(blocks.iter().all(|b| b.path() == std::path::Path::new("src/lib.rs")));
+    Ok(())
+}
+
+#[test]
+fn block_comment_trailing_code_stays_code() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "let x = 1; /* start of a\n   long trailing comment */\nlet y = 2;\n";
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    assert_eq!(blocks.len(), 1);
+    assert!(blocks[0].comment().is_empty());
+    assert_eq!(blocks[0].code(), ["let x = 1; /* start of a", "   long trailing comment */", "let y = 2;"]);
+    assert!(blocks[0].annotations().is_empty());
+    Ok(())
+}
+
+// Keeps comments of all kinds together, as long as no code comes between them
+struct MergeComments;
+
+impl Segmenter for MergeComments {
+    fn starts_block(&self, previous: Segment, next: Segment) -> bool {
+        previous == Segment::Code && next != Segment::Code
+    }
+}
+
+#[test]
+fn custom_segmenter_decides_on_blocks() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "//! The crate\n/// A function\npub fn f() {}\n// and the next\nfn g() {}\n";
+    let options = ExtractOptions {
+        segmenter: Some(Box::new(MergeComments)),
+        ..ExtractOptions::default()
+    };
+    let blocks = extract_str("lib.rs", source, &options)?;
+    let comments: Vec<_> = blocks.iter().map(|b| b.comment()).collect();
+    assert_eq!(comments, vec![&["The crate", "A function"][..], &["and the next"][..]]);
+
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    assert_eq!(blocks.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn windows_line_endings_and_byte_order_mark() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "\u{feff}/// Two lines,\\\r\n/// broken hard\r\npub fn f() {}\r\n";
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    assert_eq!(blocks[0].comment(), ["Two lines,\\", "broken hard"]);
+    assert_eq!(blocks[0].code(), ["pub fn f() {}"]);
+    assert_eq!(dadada::blocks_to_source(&blocks), source);
+
+    let html = build_html(vec![SourceFile::new("lib.rs", blocks)], options())?;
+    assert!(html.contains("<p>Two lines,<br />\nbroken hard</p>"));
+    Ok(())
+}
+
+#[test]
+fn extract_from_reader_reports_virtual_name() {
+    let source: &[u8] = b"// caf\xe9\n";
+    let err = extract_reader("expanded.rs", source, &ExtractOptions::default()).expect_err("must fail");
+    assert_eq!(err.to_string(), "`expanded.rs` is not valid UTF-8 (line 1)");
+}
+
+#[test]
+fn lossy_extraction_warns_once() -> Result<(), Box<dyn std::error::Error>> {
+    let warnings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let seen = warnings.clone();
+    let options = ExtractOptions {
+        lossy: true,
+        on_warning: Some(Box::new(move |w| seen.borrow_mut().push((w.line, w.message.clone())))),
+        ..ExtractOptions::default()
+    };
+    let source: &[u8] = b"fn f() {}\n// caf\xe9\n// cr\xe8me\n";
+    let blocks = extract_reader("latin1.rs", source, &options)?;
+    assert_eq!(blocks[1].comment(), ["caf\u{fffd}", "cr\u{fffd}me"]);
+    assert_eq!(warnings.borrow().len(), 1);
+    assert_eq!(warnings.borrow()[0].0, 2);
+    Ok(())
+}
+
+#[test]
+fn top_level_prose_keeps_doc_attributes_in_bodies() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "\
+impl Foo {
+    #[doc = \"Attribute docs\"]
+    fn a() {}
+    /// Line docs
+    fn b() {}
+}
+";
+    let options = ExtractOptions { prose: Prose::TopLevel, ..ExtractOptions::default() };
+    let blocks = extract_str("lib.rs", source, &options)?;
+    assert_eq!(blocks.len(), 1);
+    assert!(blocks[0].comment().is_empty());
+    assert_eq!(blocks[0].code().len(), 6);
+    Ok(())
+}
+
+#[test]
+fn dedents_only_whitespace_lines_share() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "// a\n//\u{3000}b\n//\t\tc\n//\td\nfn f() {}\n";
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    assert_eq!(blocks[0].comment(), [" a", "\u{3000}b", "\t\tc", "\td"]);
+
+    let blocks = extract_str("lib.rs", "//\t\tc\n//\td\n", &ExtractOptions::default())?;
+    assert_eq!(blocks[0].comment(), ["\tc", "d"]);
+    Ok(())
+}
+
+#[test]
+fn keeps_relative_comment_indentation() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "\
+/// A list:
+/// - first
+///   - nested
+///
+/// An example:
+///
+///     let indented = \"code\";
+pub fn documented() {}
+";
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    let html = build_html(vec![SourceFile::new("lib.rs", blocks)], options())?;
+    assert!(html.contains("<li>first\n<ul>\n<li>nested</li>\n</ul>\n</li>"));
+    assert!(html.contains("<pre><code>let indented = &quot;code&quot;;</code></pre>"));
+    Ok(())
+}
+
+#[test]
+fn skipped_lines_leave_gaps_in_code_lines() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "\
+/// Docs
+
+
+// dadada: skip-start
+fn hidden() {}
+// dadada: skip-end
+fn shown() {
+}
+";
+    let blocks = extract_str("lib.rs", source, &ExtractOptions::default())?;
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].code_lines(), [2, 3, 7, 8]);
+    assert_eq!(blocks[0].item().map(|i| i.path.as_str()), Some("crate::shown"));
+    Ok(())
+}
+
+#[test]
+fn blocks_know_their_items() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "\
+/// The client
+pub struct Client;
+
+impl Client {
+    /// Connect to somewhere
+    pub(crate) fn connect(&self) {}
+}
+";
+    let options = ExtractOptions {
+        module_path: Some("crate::net".to_string()),
+        ..ExtractOptions::default()
+    };
+    let blocks = extract_str("client.rs", source, &options)?;
+    let items: Vec<_> = blocks.iter()
+        .filter_map(|b| b.item())
+        .map(|i| (i.path.as_str(), i.kind, i.visibility.clone()))
+        .collect();
+    assert_eq!(items, vec![
+        ("crate::net::Client", ItemKind::Struct, Visibility::Public),
+        ("crate::net::Client::connect", ItemKind::Fn, Visibility::Crate),
+    ]);
+    Ok(())
+}
+
+// Lists files and the lines their blocks of code span
+#[derive(Default)]
+struct Outline {
+    output: String,
+}
+
+impl Renderer for Outline {
+    fn file_start(&mut self, file: &SourceFile) -> dadada::Result<()> {
+        self.output.push_str(&format!("{}\n", file.path.display()));
+        Ok(())
+    }
+
+    fn code(&mut self, block: &Block) -> dadada::Result<()> {
+        self.output.push_str(&format!("  {}-{}\n", block.code_line(), block.end_line()));
+        Ok(())
+    }
+
+    fn document_end(&mut self) -> dadada::Result<String> {
+        Ok(std::mem::take(&mut self.output))
+    }
+}
+
+#[test]
+fn custom_renderer_gets_files_and_blocks() -> Result<(), Box<dyn std::error::Error>> {
+    let source = "//! The crate\n\n/// A function\npub fn f() {\n}\n";
+    let files = vec![
+        SourceFile::new("src/lib.rs", extract_str("src/lib.rs", source, &ExtractOptions::default())?),
+        SourceFile::new("src/empty.rs", Vec::new()),
+    ];
+    assert_eq!(render(files, &mut Outline::default())?, "src/lib.rs\n  4-5\nsrc/empty.rs\n");
+    Ok(())
+}
+
+#[test]
+fn workspace_members_and_their_targets() -> Result<(), Box<dyn std::error::Error>> {
+    let packages = read_manifest("tests/fixtures/workspace/Cargo.toml")?;
+    let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["app", "core-lib"]);
+
+    let app = &packages[0];
+    assert_eq!(app.description.as_deref(), Some("The application"));
+    assert_eq!(app.authors, vec!["Jane Doe <jane@example.org>".to_string()]);
+    assert_eq!(app.config.title.as_deref(), Some("Workspace Example"));
+    assert_eq!(app.config.prose, Some(Prose::Doc));
+
+    let targets: Vec<(TargetKind, &str)> = packages[1].targets.iter()
+        .map(|t| (t.kind, t.name.as_str()))
+        .collect();
+    assert_eq!(targets, vec![
+        (TargetKind::Lib, "core_lib"),
+        (TargetKind::Example, "demo"),
+        (TargetKind::Test, "smoke"),
+    ]);
+    assert_eq!(packages[1].selected_targets().count(), 2);
+    Ok(())
+}
+
+#[test]
+fn default_packages_like_cargo() -> Result<(), Box<dyn std::error::Error>> {
+    // a virtual workspace defaults to all of its members
+    let packages = default_packages("tests/fixtures/workspace/Cargo.toml")?;
+    let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["app", "core-lib"]);
+
+    // a member defaults to itself
+    let packages = default_packages("tests/fixtures/workspace/crates/core/Cargo.toml")?;
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0].name, "core-lib");
+
+    // `default-members` take precedence over the root package
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("Cargo.toml"), "\
+[package]
+name = \"root\"
+
+[workspace]
+members = [\"crates/*\"]
+default-members = [\"crates/b\"]
+")?;
+    for member in &["a", "b"] {
+        std::fs::create_dir_all(dir.path().join("crates").join(member))?;
+        std::fs::write(dir.path().join("crates").join(member).join("Cargo.toml"),
+            format!("[package]\nname = \"{}\"\n", member))?;
+    }
+    let packages = default_packages(dir.path().join("Cargo.toml"))?;
+    let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["b"]);
+    Ok(())
+}
+
+#[test]
+fn build_script_renders_examples() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = tempfile::tempdir()?;
+    let rendered = dadada::build::render_examples_in(
+        "tests/fixtures/workspace/crates/core", out_dir.path(), &Options::default())?;
+    assert_eq!(rendered, vec![out_dir.path().join("demo.html")]);
+
+    let page = std::fs::read_to_string(&rendered[0])?;
+    assert!(page.contains("<title>demo</title>"));
+    assert!(page.contains("Shows how to say hello"));
+    Ok(())
+}
+
+#[test]
+fn build_script_renders_examples_like_cargo() -> Result<(), Box<dyn std::error::Error>> {
+    let manifest_dir = tempfile::tempdir()?;
+    let out_dir = tempfile::tempdir()?;
+    std::fs::write(manifest_dir.path().join("Cargo.toml"), "[package]\nname = \"demo\"\n")?;
+    // no `examples/` at all is no examples
+    let rendered = dadada::build::render_examples_in(manifest_dir.path(), out_dir.path(), &Options::default())?;
+    assert!(rendered.is_empty());
+
+    std::fs::write(manifest_dir.path().join("Cargo.toml"), "\
+[package]
+name = \"demo\"
+
+[[example]]
+name = \"tour\"
+path = \"guide/tour.rs\"
+")?;
+    let examples = manifest_dir.path().join("examples");
+    std::fs::create_dir_all(examples.join("server"))?;
+    std::fs::create_dir_all(manifest_dir.path().join("guide"))?;
+    std::fs::write(examples.join("server").join("main.rs"), "//! Serves\nmod routes;\nfn main() {}\n")?;
+    std::fs::write(examples.join("server").join("routes.rs"), "//! Where requests go\n")?;
+    std::fs::write(examples.join("client.rs"), "//! Asks\nfn main() {}\n")?;
+    std::fs::write(manifest_dir.path().join("guide").join("tour.rs"), "//! Shows around\nfn main() {}\n")?;
+    let rendered = dadada::build::render_examples_in(manifest_dir.path(), out_dir.path(), &Options::default())?;
+    assert_eq!(rendered, vec![
+        out_dir.path().join("tour.html"),
+        out_dir.path().join("client.html"),
+        out_dir.path().join("server.html"),
+    ]);
+
+    let page = std::fs::read_to_string(&rendered[0])?;
+    assert!(page.contains("<strong><code>tour.rs</code></strong> (in <code>guide</code>)"));
+    let page = std::fs::read_to_string(&rendered[2])?;
+    assert!(page.contains("<title>server</title>"));
+    assert!(page.contains("Serves"));
+    // with the modules it declares, linked to
+    assert!(page.contains("Where requests go"));
+    assert!(page.contains("href=\"#file.server-routes.rs\""));
+
+    // only those listed when `autoexamples` is off
+    std::fs::write(manifest_dir.path().join("Cargo.toml"), "\
+[package]
+name = \"demo\"
+autoexamples = false
+
+[[example]]
+name = \"tour\"
+path = \"guide/tour.rs\"
+")?;
+    let rendered = dadada::build::render_examples_in(manifest_dir.path(), out_dir.path(), &Options::default())?;
+    assert_eq!(rendered, vec![out_dir.path().join("tour.html")]);
+    Ok(())
+}
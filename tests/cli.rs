@@ -55,7 +55,7 @@ fn title_with_unicode_emoji() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn simple_run() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/minimal/")
@@ -77,7 +77,7 @@ fn simple_run() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn customised_run() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/extra_content/")
@@ -105,7 +105,7 @@ fn customised_run() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn no_css() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/minimal/")
@@ -127,7 +127,7 @@ fn no_css() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn no_js() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/minimal/")
@@ -150,7 +150,7 @@ fn no_js() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn no_css_nor_js() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/minimal/")
@@ -173,7 +173,7 @@ fn no_css_nor_js() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn regular_big_run_works() -> Result<(), Box<dyn std::error::Error>> {
     let file = NamedTempFile::new()?;
-    let path = file.path().to_str().unwrap().clone();
+    let path = file.path().to_str().unwrap();
     let mut cmd = Command::cargo_bin("dadada")?;
     cmd
         .current_dir("tests/fixtures/occb-exmpl/")
@@ -202,3 +202,590 @@ fn file_missing() -> Result<(), Box<dyn std::error::Error>> {
         .stderr(predicate::str::contains("No such file or directory"));
     Ok(())
 }
+
+#[test]
+fn block_comments_are_prose() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/block_comments/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<h2 id=\"block-comment-example\">Block comment example</h2>"))
+        .stdout(predicates::str::contains("<p>A doc block comment with a gutter</p>\n<p>and a second paragraph</p>"))
+        .stdout(predicates::str::contains("<p>/* nested */ still a comment</p>"))
+        .stdout(predicates::str::contains("/**").not())
+        .stdout(predicates::str::contains("/*!").not());
+
+    Ok(())
+}
+
+#[test]
+fn comment_markers_in_literals_stay_code() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/literals/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("\n// this is part of the string\n"))
+        // `"#` doesn't close `r##"`, so the `/*` after it doesn't open a comment
+        .stdout(predicates::str::contains("r##\"\n/* not a comment \"# /* either\n\"##;"))
+        .stdout(predicates::str::contains("(char, &'a str) {\n    ('\"', s)\n}"))
+        .stdout(predicates::str::contains("\n// still bytes\";"));
+
+    Ok(())
+}
+
+#[test]
+fn rust_is_highlighted_while_rendering() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/literals/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<code class=\"language-rust\" data-highlighted>\
+            <span class=\"token keyword\">pub</span> <span class=\"token keyword\">const</span> HELP"))
+        .stdout(predicates::str::contains("\n<span class=\"token string\">// this is part of the string</span>\n"))
+        .stdout(predicates::str::contains(
+            "<span class=\"token operator\">&lt;</span><span class=\"token lifetime-annotation symbol\">'a</span>"))
+        .stdout(predicates::str::contains("<span class=\"token char string\">'\"'</span>"))
+        .stdout(predicates::str::contains(
+            "<span aria-hidden=\"true\" class=\"line-numbers-rows\"><span data-line=\"2\"></span>\
+            <span data-line=\"3\"></span><span data-line=\"4\"></span><span data-line=\"5\"></span></span>"));
+
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/literals/")
+        .arg("--no-highlight")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("class=\"token").not())
+        .stdout(predicates::str::contains("data-highlighted>").not())
+        .stdout(predicates::str::contains("class=\"line-numbers-rows\"><span data-line=\"2\"></span>"));
+
+    Ok(())
+}
+
+#[test]
+fn trailing_comments_become_annotations() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/annotations/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("    let start = 1;\n"))
+        // numbered from the first line of code, not of the prose above it
+        .stdout(predicates::str::contains("<pre class=\"language-rust line-numbers\" data-start=\"2\">"))
+        .stdout(predicates::str::contains("class=\"line-numbers-rows\"><span data-line=\"2\"></span>\
+            <span data-line=\"3\"></span>"))
+        .stdout(predicates::str::contains(
+            "<div class=\"annotation\" style=\"top: 1.5em\"><a class=\"marker\" title=\"line 3\">&#8226;</a>\
+            <div class=\"note\"><p>why we start at <em>one</em></p>\n</div></div>"))
+        .stdout(predicates::str::contains(
+            "let url = \"http://example.com\";\n"))
+        .stdout(predicates::str::contains("<p>not a comment within the string</p>"));
+
+    Ok(())
+}
+
+#[test]
+fn no_annotations_keeps_comments_inline() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/annotations/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("--no-annotations")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("    let start = 1; // why we start at *one*\n"))
+        .stdout(predicates::str::contains("class=\"annotation").not());
+
+    Ok(())
+}
+
+#[test]
+fn jump_to_menu_lists_files_and_headings() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/toc/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("lib.rs")
+        .arg("more.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<h3 id=\"details\">Details</h3>"))
+        .stdout(predicates::str::contains("<h2 id=\"usage-2\">Usage</h2>"))
+        .stdout(predicates::str::contains("<h2 id=\"circle-t\"><code>Circle&lt;T&gt;</code></h2>"))
+        .stdout(predicates::str::contains("<div id=\"jump_page\">
+            <a class=\"source level-0\" href=\"#file.lib.rs\">lib.rs</a>
+            <a class=\"source level-1\" href=\"#shapes\">Shapes</a>
+            <a class=\"source level-2\" href=\"#usage\">Usage</a>
+            <a class=\"source level-2\" href=\"#usage-2\">Usage</a>
+            <a class=\"source level-0\" href=\"#file.more.rs\">more.rs</a>
+            <a class=\"source level-2\" href=\"#circle-t\">Circle&lt;T></a>
+        </div>"));
+
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/toc/")
+        .arg("--no-toc")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<h2 id=\"usage\">Usage</h2>"))
+        .stdout(predicates::str::contains("jump_to\">").not());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_utf8_reports_file_and_line() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/errors/")
+        .arg("latin1.rs");
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("`latin1.rs` is not valid UTF-8 (line 1)"))
+        .stderr(predicate::str::contains("--lossy"))
+        .stderr(predicate::str::contains("panicked").not());
+    Ok(())
+}
+
+#[test]
+fn lossy_decodes_invalid_utf8_with_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/errors/")
+        .arg("--lossy")
+        .arg("latin1.rs");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("warning: latin1.rs:1: not valid UTF-8"))
+        .stdout(predicate::str::contains("Caf\u{fffd} au lait"));
+    Ok(())
+}
+
+#[test]
+fn missing_include_reports_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--header")
+        .arg("not_existing.html")
+        .arg("lib.rs");
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("could not include `not_existing.html`"));
+    Ok(())
+}
+
+#[test]
+fn template_dir_overrides_built_in_templates() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--template-dir")
+        .arg("../templates/sections")
+        .arg("--no-highlight")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<section id=\"file.lib.rs\" data-index=\"0\"><h2>lib.rs</h2>\n</section>"))
+        .stdout(predicate::str::contains("<section id=\"section-2\" data-index=\"2\"><p>some imports</p>"))
+        .stdout(predicate::str::contains("<pre data-lines=\"5-11\">use std::fs::File;"))
+        // not overridden, so still the built-in one
+        .stdout(predicate::str::contains("<title>"))
+        .stdout(predicate::str::contains("class=\"block\"").not());
+    Ok(())
+}
+
+#[test]
+fn broken_template_reports_its_name() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--template-dir")
+        .arg("../templates/broken")
+        .arg("lib.rs");
+    cmd.assert()
+        .code(5)
+        .stderr(predicate::str::contains("failed to render template `block_code.html`: syntax error"));
+    Ok(())
+}
+
+#[test]
+fn reads_source_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("-")
+        .with_stdin()
+        .buffer("// Generated in memory\nfn generated() {}\n")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("<strong><code>stdin</code></strong>"))
+        .stdout(predicates::str::contains("<p>Generated in memory</p>"))
+        .stdout(predicates::str::contains("<code>crate::stdin::generated</code>"))
+        .stdout(predicates::str::contains("fn generated() {}"));
+    Ok(())
+}
+
+#[test]
+fn comment_syntax_by_file_extension() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/languages/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("Cargo.toml")
+        .arg("migrate.sql")
+        .arg("ffi.c");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<p>The package manifest</p>"))
+        .stdout(predicates::str::contains("<code class=\"language-toml\">[package]"))
+        .stdout(predicates::str::contains("homepage = \"https://example.com/#not-a-comment\""))
+        .stdout(predicates::str::contains("<p>Literal strings have no escapes</p>"))
+        // `\` doesn't escape the closing quote of a literal string
+        .stdout(predicates::str::contains("<p>Built somewhere else</p>"))
+        .stdout(predicates::str::contains("<p>Create the users table</p>"))
+        .stdout(predicates::str::contains("DEFAULT '-- not a comment');"))
+        .stdout(predicates::str::contains("<p>Block comments\nwork in SQL too</p>"))
+        .stdout(predicates::str::contains("<p>Exposed to Rust via FFI</p>"))
+        .stdout(predicates::str::contains("<code class=\"language-c\">int answer"));
+
+    Ok(())
+}
+
+#[test]
+fn lang_overrides_detection() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/languages/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--lang")
+        .arg("bash")
+        .arg("build.script");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<p>Run with <code>--lang bash</code></p>"))
+        .stdout(predicates::str::contains(
+            "<code class=\"language-bash\">echo \"# not a comment\"\n\
+            echo ${#args[@]} http://example.com/#not-a-comment-either\n\
+            echo 'C:\\'"))
+        .stdout(predicates::str::contains("<p>Done</p>"))
+        .stdout(predicates::str::contains("class=\"annotation").not());
+
+    Ok(())
+}
+
+#[test]
+fn doc_attributes_are_prose() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("doc_attributes/lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<h1 id=\"doc-attributes\">Doc Attributes</h1>\n<p>The crate documentation lives in the README.</p>"))
+        .stdout(predicates::str::contains("<p>Generated by a macro,\nspanning two lines</p>"))
+        .stdout(predicates::str::contains("#[doc = ").not())
+        .stdout(predicates::str::contains("#[doc(hidden)]"));
+
+    Ok(())
+}
+
+#[test]
+fn directives_skip_hide_and_break() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/directives/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Copyright").not())
+        .stdout(predicates::str::contains("mod tests").not())
+        .stdout(predicates::str::contains("scaffolding").not())
+        .stdout(predicates::str::contains("dadada:").not())
+        .stdout(predicates::str::contains("    let a = 1;<span aria-hidden=\"true\" class=\"line-numbers-rows\">"))
+        .stdout(predicates::str::contains(
+            "<pre class=\"language-rust line-numbers\" data-start=\"20\"><code class=\"language-rust\">    let b = 2;"))
+        // hidden lines leave a gap in the numbers
+        .stdout(predicates::str::contains("<span data-line=\"7\"></span><span data-line=\"15\"></span>"))
+        .stderr(predicates::str::contains("warning: lib.rs:23: unknown directive `frobnicate`"));
+
+    Ok(())
+}
+
+#[test]
+fn prose_doc_keeps_plain_comments_in_code() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("--prose")
+        .arg("doc")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("\n// some imports\nuse std::fs::File;"))
+        .stdout(predicates::str::contains("<p>With a doc comment</p>"))
+        .stdout(predicates::str::contains("<p>some imports</p>").not());
+
+    Ok(())
+}
+
+#[test]
+fn prose_top_level_keeps_bodies_together() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("--prose")
+        .arg("top-level")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<p>some imports</p>"))
+        .stdout(predicates::str::contains(
+            "    for i in ..100 {\n        // ! We do not mind, that this isn't actually doing anything\n    }"))
+        .stdout(predicates::str::contains("    /// With a doc comment\n    starting_line: usize,"));
+
+    Ok(())
+}
+
+#[test]
+fn item_anchors_and_headings() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/occb-exmpl/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("offchaincb.rs")
+        .arg("lib.rs");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<div id=\"fn.crate.offchaincb.Module.offchain\" class=\"docs\">"))
+        .stdout(predicates::str::contains("<a class=\"pilcrow\" href=\"#fn.crate.offchaincb.Module.offchain\">"))
+        .stdout(predicates::str::contains(
+            "<div class=\"item-heading\"><span class=\"item-kind\">pub trait</span> <code>crate::offchaincb::Trait</code></div>"))
+        // the same path twice gets a distinct anchor
+        .stdout(predicates::str::contains("<div id=\"impl.crate.Runtime-2\" class=\"docs\">"));
+
+    Ok(())
+}
+
+#[test]
+fn crate_in_module_order() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/crate_walk/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--crate")
+        .arg("src/lib.rs");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    let files: Vec<usize> = ["lib.rs", "util.rs", "inner.rs", "mod.rs", "client.rs", "unix.rs", "generated.rs"]
+        .iter()
+        .map(|f| output.find(&format!("<strong><code>{}</code></strong>", f)).expect(f))
+        .collect();
+    assert!(files.windows(2).all(|w| w[0] < w[1]), "files out of module order");
+
+    assert!(output.contains("<div id=\"file.src-platform-unix.rs\" class=\"docs\">"));
+    assert!(output.contains("href=\"#file.src-platform-unix.rs\""));
+    // declared after lines that are skipped
+    assert!(output.contains("href=\"#file.src-net-mod.rs\""));
+    assert!(output.contains("href=\"#file.src-helpers-generated.rs\""));
+    assert!(output.contains("href=\"#file.src-util-inner.rs\""));
+
+    Ok(())
+}
+
+#[test]
+fn manifest_path_renders_workspace() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace/")
+        .arg("--no-css")
+        .arg("--no-js")
+        .arg("--no-highlight")
+        .arg("--manifest-path")
+        .arg("Cargo.toml");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("<title>Workspace Example</title>"))
+        .stdout(predicates::str::contains("<h1 id=\"core-lib\">core-lib</h1>"))
+        .stdout(predicates::str::contains("<h2 id=\"example-demo\">example <code>demo</code></h2>"))
+        .stdout(predicates::str::contains("<em>by Jane Doe"))
+        // `prose = "doc"` from the package metadata
+        .stdout(predicates::str::contains("    // not prose, as only doc comments are"))
+        .stdout(predicates::str::contains("smoke").not())
+        .stdout(predicates::str::contains("scratch").not());
+
+    Ok(())
+}
+
+#[test]
+fn output_dir_renders_a_page_per_file() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/crate_walk")
+        .arg("--crate")
+        .arg("src/lib.rs")
+        .arg("--output-dir")
+        .arg(out_dir.path());
+    cmd.assert().success();
+
+    let index = std::fs::read_to_string(out_dir.path().join("index.html"))?;
+    assert!(index.contains("<link rel=\"stylesheet\" href=\"dadada.css\">"));
+    assert!(index.contains("<a href=\"net/mod.rs.html\"><code>src/net/mod.rs</code></a><p>Networking</p>"));
+    assert!(out_dir.path().join("dadada.js").is_file());
+
+    let page = std::fs::read_to_string(out_dir.path().join("net/mod.rs.html"))?;
+    assert!(page.contains("<title>src/net/mod.rs</title>"));
+    assert!(page.contains("<script src=\"../dadada.js\"></script>"));
+    assert!(!page.contains("<style>"));
+    assert!(page.contains("<a class=\"prev\" href=\"../util/inner.rs.html\">"));
+    assert!(page.contains("<a class=\"index\" href=\"../index.html\">"));
+    assert!(page.contains("<a class=\"next\" href=\"../net/client.rs.html\">"));
+    assert!(page.contains("href=\"../net/client.rs.html#file.src-net-client.rs\""));
+
+    Ok(())
+}
+
+#[test]
+fn cargo_subcommand_renders_selected_packages() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("cargo-dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace/crates/core")
+        .arg("dadada")
+        .arg("--workspace")
+        .arg("--out-dir")
+        .arg(out_dir.path());
+    cmd.assert().success();
+    assert!(out_dir.path().join("app.html").is_file());
+    assert!(out_dir.path().join("core-lib.html").is_file());
+
+    let out_dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("cargo-dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace/crates/core")
+        .arg("dadada")
+        .arg("--example")
+        .arg("demo")
+        .arg("--out-dir")
+        .arg(out_dir.path());
+    cmd.assert().success();
+    let page = std::fs::read_to_string(out_dir.path().join("core-lib.html"))?;
+    assert!(page.contains("<h2 id=\"example-demo\">example <code>demo</code></h2>"));
+    assert!(!page.contains("<h2 id=\"lib-core-lib\">lib <code>core_lib</code></h2>"));
+    assert!(!out_dir.path().join("app.html").exists());
+
+    Ok(())
+}
+
+#[test]
+fn cargo_subcommand_renders_all_members_of_a_virtual_workspace() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("cargo-dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace")
+        .arg("dadada")
+        .arg("--out-dir")
+        .arg(out_dir.path());
+    cmd.assert().success();
+    assert!(out_dir.path().join("app.html").is_file());
+    assert!(out_dir.path().join("core-lib.html").is_file());
+
+    Ok(())
+}
+
+#[test]
+fn cargo_subcommand_writes_to_the_target_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let target_dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("cargo-dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace/crates/core")
+        .env("CARGO_TARGET_DIR", target_dir.path())
+        .arg("dadada");
+    cmd.assert().success();
+    assert!(target_dir.path().join("dadada").join("core-lib.html").is_file());
+    assert!(!std::path::Path::new("tests/fixtures/workspace/target").exists());
+
+    Ok(())
+}
+
+#[test]
+fn cargo_subcommand_unknown_package() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("cargo-dadada")?;
+    cmd
+        .current_dir("tests/fixtures/workspace")
+        .arg("dadada")
+        .arg("-p")
+        .arg("nope");
+    cmd.assert()
+        .failure()
+        .code(6)
+        .stderr(predicates::str::contains("package `nope` is not a member of the workspace"));
+
+    Ok(())
+}
+
+#[test]
+fn json_format_lists_files_and_blocks() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("dadada")?;
+    cmd
+        .current_dir("tests/fixtures/minimal/")
+        .arg("--format")
+        .arg("json")
+        .arg("lib.rs");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let document: dadada::json::Document = serde_json::from_slice(&output)?;
+
+    assert_eq!(document.version, dadada::json::VERSION);
+    assert_eq!(document.files.len(), 1);
+    assert_eq!(document.files[0].path, "lib.rs");
+    let block = &document.files[0].blocks[1];
+    assert_eq!(block.comment, "some imports");
+    assert_eq!(block.comment_html, "<p>some imports</p>\n");
+    assert!(block.code.starts_with("use std::fs::File;"));
+    assert_eq!((block.start_line, block.end_line), (5, 11));
+    assert_eq!(block.comment_kind, Some(dadada::CommentKind::Line));
+
+    Ok(())
+}
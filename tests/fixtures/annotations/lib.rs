@@ -0,0 +1,6 @@
+/// Counting from one, with notes on the side
+pub fn count() -> usize {
+    let start = 1; // why we start at *one*
+    let url = "http://example.com"; /* not a comment within the string */
+    start
+}
@@ -0,0 +1,18 @@
+/*!
+ * ## Block comment example
+ * Crate level docs in a block comment
+ */
+
+/* A plain block comment
+   spanning two lines */
+use std::fs::File;
+
+/**
+ * A doc block comment with a gutter
+ *
+ * and a second paragraph
+ */
+pub struct Block {
+    /* /* nested */ still a comment */
+    code: Vec<String>,
+}
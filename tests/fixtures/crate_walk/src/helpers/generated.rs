@@ -0,0 +1,2 @@
+//! Found in `helpers/generated.rs`
+pub const GENERATED: bool = true;
@@ -0,0 +1,16 @@
+//! A crate to walk, in the order its modules are declared
+
+/// Small helpers
+mod util;
+// dadada: skip-start
+// nothing to see here
+// dadada: skip-end
+pub mod net;
+
+#[path = "platform/unix.rs"]
+mod sys;
+
+/// Code we generate, declared inline
+mod helpers {
+    mod generated;
+}
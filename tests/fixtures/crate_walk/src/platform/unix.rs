@@ -0,0 +1,2 @@
+//! Found through `#[path]`
+pub fn pid() -> u32 { 0 }
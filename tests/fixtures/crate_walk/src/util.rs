@@ -0,0 +1,4 @@
+//! Utilities, with a submodule of their own
+mod inner;
+
+pub fn noop() {}
@@ -0,0 +1,2 @@
+//! Found in `util/inner.rs`
+pub fn inner() {}
@@ -0,0 +1,24 @@
+// dadada: skip-start
+// Copyright (c) Somebody, licensed under whatever
+// dadada: skip-end
+
+//! Directives at work
+use std::fmt;
+
+// dadada: hide-next-item
+/// Test scaffolding nobody needs to read
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn works() {}
+}
+
+/// A long function
+pub fn long() {
+    let a = 1;
+    // dadada: break
+    let b = 2;
+}
+
+// dadada: frobnicate
+pub struct After;
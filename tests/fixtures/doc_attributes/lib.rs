@@ -0,0 +1,7 @@
+#![doc = include_str!("README.md")]
+
+#[doc = "Generated by a macro,\nspanning two lines"]
+pub struct Generated;
+
+#[doc(hidden)]
+pub struct Hidden;
@@ -0,0 +1,2 @@
+// Caf au lait
+fn main() {}
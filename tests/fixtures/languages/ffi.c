@@ -0,0 +1,4 @@
+/** Exposed to Rust via FFI */
+int answer(void) {
+    return 42;
+}
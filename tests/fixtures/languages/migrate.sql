@@ -0,0 +1,5 @@
+-- Create the users table
+CREATE TABLE users (id INTEGER, name TEXT DEFAULT '-- not a comment');
+/* Block comments
+   work in SQL too */
+DROP TABLE legacy;
@@ -0,0 +1,16 @@
+//! Comment markers within literals are code
+pub const HELP: &str = "usage:
+// this is part of the string
+    and so is this";
+
+/// A raw string opening something that looks like a block comment
+pub const RAW: &str = r##"
+/* not a comment "# /* either
+"##;
+
+pub fn quote<'a>(s: &'a str) -> (char, &'a str) {
+    ('"', s)
+}
+
+pub const BYTES: &[u8] = br"
+// still bytes";
@@ -0,0 +1,14 @@
+//! # Shapes
+//!
+//! ## Usage
+//!
+//! Build one and ask for its area.
+//!
+//! ### Details
+//!
+//! Too deep for the menu.
+
+/// ## Usage
+///
+/// The same heading a second time.
+pub struct Square(pub f64);
@@ -0,0 +1,2 @@
+// ## `Circle<T>`
+pub struct Circle(pub f64);
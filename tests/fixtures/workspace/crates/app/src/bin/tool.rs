@@ -0,0 +1,2 @@
+//! A little tool
+fn main() {}
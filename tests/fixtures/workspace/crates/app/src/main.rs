@@ -0,0 +1,5 @@
+//! Runs the application
+fn main() {
+    // not prose, as only doc comments are
+    core_lib::hello();
+}
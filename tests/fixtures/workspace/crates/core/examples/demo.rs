@@ -0,0 +1,4 @@
+//! Shows how to say hello
+fn main() {
+    core_lib::hello();
+}
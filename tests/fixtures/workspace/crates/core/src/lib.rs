@@ -0,0 +1,2 @@
+//! The core of it all
+pub fn hello() {}
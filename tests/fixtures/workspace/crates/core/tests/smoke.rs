@@ -0,0 +1,3 @@
+//! Not rendered, only lib and examples are
+#[test]
+fn smoke() {}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dadada::{ExtractOptions, Prose, blocks_to_source, extract, extract_str};
+use proptest::prelude::*;
+
+// Every source file among the fixtures, whatever its language
+fn fixtures() -> Vec<PathBuf> {
+    fn walk(dir: &Path, found: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).expect("fixtures are readable").flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(&path, found);
+            } else if !matches!(path.extension().and_then(|e| e.to_str()), Some("html") | Some("md")) {
+                found.push(path);
+            }
+        }
+    }
+    let mut found = Vec::new();
+    walk(Path::new("tests/fixtures"), &mut found);
+    found.sort();
+    found
+}
+
+// All lines of all fixtures that are valid UTF-8, to build sources from
+fn fixture_lines() -> Vec<String> {
+    fixtures().iter()
+        .filter_map(|f| fs::read_to_string(f).ok())
+        .flat_map(|s| s.lines().map(|l| l.to_string()).collect::<Vec<_>>())
+        .collect()
+}
+
+fn quiet(prose: Prose) -> ExtractOptions {
+    ExtractOptions {
+        on_warning: Some(Box::new(|_| {})),
+        prose,
+        ..ExtractOptions::default()
+    }
+}
+
+#[test]
+fn fixtures_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    for fixture in fixtures() {
+        let source = match fs::read_to_string(&fixture) {
+            Ok(source) => source,
+            // not UTF-8, on purpose
+            Err(_) => continue,
+        };
+        let path = fixture.to_string_lossy().into_owned();
+        let blocks = extract(path, &quiet(Prose::All))?;
+        assert_eq!(blocks_to_source(&blocks), source, "{}", fixture.display());
+    }
+    Ok(())
+}
+
+// Sources made up of fixture lines and random ones, in random order and with
+// mixed line endings.
+fn sources() -> impl Strategy<Value=String> {
+    let lines = fixture_lines();
+    let line = prop_oneof![
+        3 => proptest::sample::select(lines),
+        1 => "[ -~]{0,30}",
+        // comments indented by all kinds of whitespace
+        1 => "//[ \t\u{a0}\u{3000}]{0,3}[ -~]{0,20}",
+    ];
+    let ending = prop_oneof![Just("\n"), Just("\r\n"), Just("")];
+    proptest::collection::vec((line, ending), 0..40).prop_map(|lines| {
+        let count = lines.len();
+        lines.into_iter().enumerate()
+            // only the last line may go without an ending
+            .map(|(i, (line, ending))| match ending {
+                "" if i + 1 < count => format!("{}\n", line),
+                _ => format!("{}{}", line, ending),
+            })
+            .collect()
+    })
+}
+
+proptest! {
+    #[test]
+    fn any_source_round_trips(
+        source in sources(),
+        name in proptest::sample::select(vec!["lib.rs", "migrate.sql", "ffi.c", "Cargo.toml"]),
+        prose in prop_oneof![Just(Prose::All), Just(Prose::Doc), Just(Prose::TopLevel)],
+    ) {
+        let blocks = extract_str(name, &source, &quiet(prose));
+        // including files relative to `name` fails, which isn't what we test
+        prop_assume!(blocks.is_ok());
+        prop_assert_eq!(blocks_to_source(&blocks.expect("checked")), source);
+    }
+}